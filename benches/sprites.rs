@@ -0,0 +1,240 @@
+//! Benchmarks of the sprite render path.
+//!
+//! The sprite counts can be configured with a comma separated list in the
+//! `SPRITE_EX_BENCH_COUNTS` environment variable. The `queue` and `prepare` benchmarks render
+//! through a headless renderer and need a GPU adapter (a software adapter works).
+
+use bevy::{
+    ecs::system::RunSystemOnce,
+    prelude::*,
+    render::{
+        camera::RenderTarget,
+        pipelined_rendering::PipelinedRenderingPlugin,
+        render_asset::RenderAssetUsages,
+        render_phase::ViewSortedRenderPhases,
+        render_resource::{Extent3d, TextureDimension, TextureFormat, TextureUsages},
+        view::NoFrustumCulling,
+        ExtractSchedule, MainWorld, RenderApp,
+    },
+    utils::{Duration, Instant},
+    window::ExitCondition,
+    winit::WinitPlugin,
+};
+use bevy_core_pipeline::core_2d::Transparent2d;
+use bevy_sprite_ex::{
+    extract_sprites, prepare_sprite_image_bind_groups, queue_sprites, write_sprite_buffers,
+    ExtractedSprites, SpriteEx, SpriteExBundle, SpriteExPlugin, SpriteSamplers,
+};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+
+/// The number of distinct images the sprites are spread over.
+const TEXTURE_COUNT: usize = 8;
+
+fn sprite_counts() -> Vec<usize> {
+    std::env::var("SPRITE_EX_BENCH_COUNTS")
+        .ok()
+        .map(|counts| {
+            counts
+                .split(',')
+                .filter_map(|count| count.trim().parse().ok())
+                .collect()
+        })
+        .unwrap_or_else(|| vec![1_000, 10_000, 50_000])
+}
+
+fn texture(index: usize) -> Image {
+    let mut image = Image::new_fill(
+        Extent3d {
+            width: 16,
+            height: 16,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        &[(index * 31) as u8, 128, 255, 255],
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::default(),
+    );
+    image.texture_descriptor.usage |= TextureUsages::RENDER_ATTACHMENT;
+    image
+}
+
+fn spawn_sprites(world: &mut World, count: usize) {
+    let textures: Vec<Handle<Image>> = {
+        let mut images = world.resource_mut::<Assets<Image>>();
+        (0..TEXTURE_COUNT)
+            .map(|index| images.add(texture(index)))
+            .collect()
+    };
+    let columns = (count as f32).sqrt().ceil() as usize;
+    // Every sprite is drawn even though most lie outside of the small render target
+    world.spawn_batch((0..count).map(move |index| {
+        (
+            SpriteExBundle {
+                sprite: SpriteEx {
+                    custom_size: Some(Vec2::splat(4.0)),
+                    ..default()
+                },
+                transform: Transform::from_xyz(
+                    ((index % columns) as f32 - columns as f32 / 2.0) * 4.0,
+                    ((index / columns) as f32 - columns as f32 / 2.0) * 4.0,
+                    (index % TEXTURE_COUNT) as f32,
+                ),
+                texture: textures[index % TEXTURE_COUNT].clone(),
+                ..default()
+            },
+            NoFrustumCulling,
+        )
+    }));
+}
+
+fn extraction(c: &mut Criterion) {
+    let mut group = c.benchmark_group("extract_sprites");
+    for count in sprite_counts() {
+        let mut main_world = World::new();
+        main_world.init_resource::<Assets<Image>>();
+        spawn_sprites(&mut main_world, count);
+        // Visibility is computed by the main app, mark every sprite as visible instead
+        main_world.run_system_once(|mut visibilities: Query<&mut ViewVisibility>| {
+            for mut visibility in &mut visibilities {
+                visibility.set();
+            }
+        });
+
+        let mut render_world = World::new();
+        render_world.init_resource::<ExtractedSprites>();
+        render_world.init_resource::<SpriteSamplers>();
+        let mut inserted_world = MainWorld::default();
+        *inserted_world = main_world;
+        render_world.insert_resource(inserted_world);
+
+        let mut system = IntoSystem::into_system(extract_sprites);
+        system.initialize(&mut render_world);
+        group.bench_with_input(BenchmarkId::from_parameter(count), &count, |b, _| {
+            b.iter(|| system.run((), &mut render_world));
+        });
+    }
+    group.finish();
+}
+
+/// Creates a headless app rendering `count` sprites to an offscreen image, and runs it until the
+/// sprite images are uploaded.
+fn headless_app(count: usize) -> App {
+    let mut app = App::new();
+    app.add_plugins((
+        DefaultPlugins
+            .build()
+            .disable::<WinitPlugin>()
+            // The render app is driven manually by the benchmarks
+            .disable::<PipelinedRenderingPlugin>()
+            .set(WindowPlugin {
+                primary_window: None,
+                exit_condition: ExitCondition::DontExit,
+                ..default()
+            }),
+        SpriteExPlugin::default(),
+    ));
+    app.finish();
+    app.cleanup();
+
+    let target = app
+        .world_mut()
+        .resource_mut::<Assets<Image>>()
+        .add(texture(0));
+    app.world_mut().spawn(Camera2dBundle {
+        camera: Camera {
+            target: RenderTarget::Image(target),
+            ..default()
+        },
+        ..default()
+    });
+    spawn_sprites(app.world_mut(), count);
+    for _ in 0..3 {
+        app.update();
+    }
+    app
+}
+
+/// Extracts a frame into the render app and returns the time spent in `measure`.
+/// Render world entities are cleared afterwards, as done at the end of each rendered frame.
+fn measure_frame(
+    app: &mut App,
+    render_app: &mut SubApp,
+    measure: &mut dyn FnMut(&mut World) -> Duration,
+) -> Duration {
+    render_app.extract(app.world_mut());
+    let world = render_app.world_mut();
+    // Commands of the extract systems are normally applied by the render schedule
+    world.resource_scope(|world, mut schedules: Mut<Schedules>| {
+        schedules
+            .get_mut(ExtractSchedule)
+            .unwrap()
+            .apply_deferred(world);
+    });
+    let elapsed = measure(world);
+    world.clear_entities();
+    elapsed
+}
+
+fn queue_and_prepare(c: &mut Criterion) {
+    let mut queue_group = c.benchmark_group("queue_sprites");
+    for count in sprite_counts() {
+        let mut app = headless_app(count);
+        let mut render_app = app.remove_sub_app(RenderApp).unwrap();
+        let mut queue = IntoSystem::into_system(queue_sprites);
+        queue.initialize(render_app.world_mut());
+
+        queue_group.bench_with_input(BenchmarkId::from_parameter(count), &count, |b, _| {
+            b.iter_custom(|iterations| {
+                (0..iterations)
+                    .map(|_| {
+                        measure_frame(&mut app, &mut render_app, &mut |world| {
+                            let start = Instant::now();
+                            queue.run((), world);
+                            start.elapsed()
+                        })
+                    })
+                    .sum()
+            });
+        });
+    }
+    queue_group.finish();
+
+    let mut prepare_group = c.benchmark_group("prepare_sprite_image_bind_groups");
+    for count in sprite_counts() {
+        let mut app = headless_app(count);
+        let mut render_app = app.remove_sub_app(RenderApp).unwrap();
+        let mut queue = IntoSystem::into_system(queue_sprites);
+        queue.initialize(render_app.world_mut());
+        let mut prepare = IntoSystem::into_system(prepare_sprite_image_bind_groups);
+        prepare.initialize(render_app.world_mut());
+        let mut write = IntoSystem::into_system(write_sprite_buffers);
+        write.initialize(render_app.world_mut());
+
+        prepare_group.bench_with_input(BenchmarkId::from_parameter(count), &count, |b, _| {
+            b.iter_custom(|iterations| {
+                (0..iterations)
+                    .map(|_| {
+                        measure_frame(&mut app, &mut render_app, &mut |world| {
+                            queue.run((), world);
+                            for phase in world
+                                .resource_mut::<ViewSortedRenderPhases<Transparent2d>>()
+                                .values_mut()
+                            {
+                                phase.sort();
+                            }
+                            let start = Instant::now();
+                            prepare.run((), world);
+                            prepare.apply_deferred(world);
+                            write.run((), world);
+                            start.elapsed()
+                        })
+                    })
+                    .sum()
+            });
+        });
+    }
+    prepare_group.finish();
+}
+
+criterion_group!(benches, extraction, queue_and_prepare);
+criterion_main!(benches);
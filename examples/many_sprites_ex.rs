@@ -0,0 +1,124 @@
+//! Renders a large number of sprites to stress test the render path.
+//!
+//! Usage: `cargo run --release --example many_sprites_ex -- [sprite count] [texture count]`
+//!
+//! Sprites are spread over the given number of distinct textures (1 by default), which controls
+//! how many batches are needed to draw them. Frame times are logged to the console.
+
+use bevy::{
+    diagnostic::{FrameTimeDiagnosticsPlugin, LogDiagnosticsPlugin},
+    prelude::*,
+    render::{
+        render_asset::RenderAssetUsages,
+        render_resource::{Extent3d, TextureDimension, TextureFormat},
+    },
+    window::{PresentMode, WindowResolution},
+};
+use bevy_sprite_ex::{SpriteEx, SpriteExBundle, SpriteExPlugin};
+
+const SPRITE_SIZE: f32 = 8.0;
+
+#[derive(Resource)]
+struct StressSettings {
+    sprite_count: usize,
+    texture_count: usize,
+}
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+    let sprite_count = args
+        .next()
+        .and_then(|arg| arg.parse().ok())
+        .unwrap_or(100_000);
+    let texture_count = args
+        .next()
+        .and_then(|arg| arg.parse().ok())
+        .unwrap_or(1usize)
+        .max(1);
+
+    App::new()
+        .add_plugins((
+            DefaultPlugins.set(WindowPlugin {
+                primary_window: Some(Window {
+                    present_mode: PresentMode::AutoNoVsync,
+                    resolution: WindowResolution::new(1920.0, 1080.0)
+                        .with_scale_factor_override(1.0),
+                    ..default()
+                }),
+                ..default()
+            }),
+            FrameTimeDiagnosticsPlugin,
+            LogDiagnosticsPlugin::default(),
+            SpriteExPlugin::default(),
+        ))
+        .insert_resource(StressSettings {
+            sprite_count,
+            texture_count,
+        })
+        .add_systems(Startup, setup)
+        .add_systems(Update, (move_camera, tint_sprites))
+        .run();
+}
+
+fn setup(mut commands: Commands, settings: Res<StressSettings>, mut images: ResMut<Assets<Image>>) {
+    info!(
+        "Spawning {} sprites using {} textures",
+        settings.sprite_count, settings.texture_count
+    );
+
+    let textures: Vec<Handle<Image>> = (0..settings.texture_count)
+        .map(|index| {
+            let hue = index as f32 / settings.texture_count as f32 * 360.0;
+            let color = Color::hsl(hue, 0.8, 0.6).to_srgba().to_u8_array();
+            images.add(Image::new_fill(
+                Extent3d {
+                    width: 16,
+                    height: 16,
+                    depth_or_array_layers: 1,
+                },
+                TextureDimension::D2,
+                &color,
+                TextureFormat::Rgba8UnormSrgb,
+                RenderAssetUsages::RENDER_WORLD,
+            ))
+        })
+        .collect();
+
+    commands.spawn(Camera2dBundle::default());
+
+    let columns = (settings.sprite_count as f32).sqrt().ceil() as usize;
+    let offset = columns as f32 * SPRITE_SIZE / 2.0;
+    commands.spawn_batch((0..settings.sprite_count).map(move |index| {
+        let (column, row) = (index % columns, index / columns);
+        SpriteExBundle {
+            sprite: SpriteEx {
+                custom_size: Some(Vec2::splat(SPRITE_SIZE)),
+                ..default()
+            },
+            transform: Transform::from_xyz(
+                column as f32 * SPRITE_SIZE - offset,
+                row as f32 * SPRITE_SIZE - offset,
+                // Interleave the textures in depth so batching is exercised
+                (index % textures.len()) as f32,
+            ),
+            texture: textures[index % textures.len()].clone(),
+            ..default()
+        }
+    }));
+}
+
+fn move_camera(time: Res<Time>, mut camera: Query<&mut Transform, With<Camera>>) {
+    let Ok(mut transform) = camera.get_single_mut() else {
+        return;
+    };
+    transform.rotate_z(time.delta_seconds() * 0.5);
+    let scale = 1.5 + time.elapsed_seconds().sin();
+    transform.scale = Vec3::new(scale, scale, 1.0);
+}
+
+fn tint_sprites(time: Res<Time>, mut sprites: Query<&mut SpriteEx>) {
+    let brightness = 0.75 + 0.25 * time.elapsed_seconds().sin();
+    for mut sprite in &mut sprites {
+        sprite.color = Color::srgb(brightness, brightness, brightness);
+    }
+}
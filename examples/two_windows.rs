@@ -0,0 +1,100 @@
+//! Renders the same sprites to two windows with different view settings.
+//!
+//! Usage: `cargo run --example two_windows`
+//!
+//! The primary window draws tiny sprites through the simplified LOD pipeline, while the second
+//! window is an HDR tool view that always draws the full sprite effects, brighter through its
+//! [`SpriteExposure`].
+
+use bevy::{
+    prelude::*,
+    render::{
+        camera::RenderTarget,
+        render_asset::RenderAssetUsages,
+        render_resource::{Extent3d, TextureDimension, TextureFormat},
+    },
+    window::WindowRef,
+};
+use bevy_sprite_ex::{
+    Border, SpriteEx, SpriteExBundle, SpriteExPlugin, SpriteExposure, SpriteLodThreshold,
+};
+
+fn main() {
+    App::new()
+        .add_plugins((
+            DefaultPlugins.set(WindowPlugin {
+                primary_window: Some(Window {
+                    title: "Game".into(),
+                    ..default()
+                }),
+                ..default()
+            }),
+            SpriteExPlugin {
+                lod_threshold: Some(16.0),
+                ..default()
+            },
+        ))
+        .add_systems(Startup, setup)
+        .add_systems(Update, spin_sprites)
+        .run();
+}
+
+fn setup(mut commands: Commands, mut images: ResMut<Assets<Image>>) {
+    commands.spawn(Camera2dBundle::default());
+
+    let tool_window = commands
+        .spawn(Window {
+            title: "Tool".into(),
+            ..default()
+        })
+        .id();
+    commands.spawn((
+        Camera2dBundle {
+            camera: Camera {
+                target: RenderTarget::Window(WindowRef::Entity(tool_window)),
+                hdr: true,
+                ..default()
+            },
+            ..default()
+        },
+        SpriteLodThreshold(None),
+        SpriteExposure(1.5),
+    ));
+
+    let texture = images.add(Image::new_fill(
+        Extent3d {
+            width: 16,
+            height: 16,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        &[230, 180, 80, 255],
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::RENDER_WORLD,
+    ));
+    for index in 0..64 {
+        let (column, row) = ((index % 8) as f32, (index / 8) as f32);
+        let size = 8.0 + column * 8.0;
+        commands.spawn((
+            SpriteExBundle {
+                sprite: SpriteEx {
+                    custom_size: Some(Vec2::splat(size)),
+                    ..default()
+                },
+                transform: Transform::from_xyz(column * 72.0 - 252.0, row * 72.0 - 252.0, 0.0),
+                texture: texture.clone(),
+                ..default()
+            },
+            Border {
+                thickness: 2.0,
+                color: Color::BLACK,
+            },
+        ));
+    }
+}
+
+fn spin_sprites(time: Res<Time>, mut sprites: Query<&mut Transform, With<SpriteEx>>) {
+    for mut transform in &mut sprites {
+        transform.rotate_z(time.delta_seconds());
+    }
+}
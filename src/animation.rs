@@ -0,0 +1,514 @@
+use std::ops::Range;
+
+use bevy_asset::{
+    io::Reader, Asset, AssetId, AssetLoader, Assets, AsyncReadExt, Handle, LoadContext,
+};
+use bevy_ecs::prelude::*;
+use bevy_hierarchy::DespawnRecursiveExt;
+use bevy_math::{Rect, UVec2};
+use bevy_reflect::{std_traits::ReflectDefault, Reflect, TypePath};
+use bevy_time::Time;
+use bevy_utils::{Duration, HashMap};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::SpriteEx;
+
+/// Flipbook animation of a sprite, stepping its [`SpriteEx::rect`] through `frames` at `fps`, or
+/// through the frames of `clip` once it is loaded.
+///
+/// The frames are interpreted in the sprite's [`SpriteEx::rect_space`], and the rect is only written
+/// when the frame changes, so sprites showing a frame for a while aren't marked as changed.
+#[derive(Component, Debug, Clone, PartialEq, Reflect)]
+#[reflect(Component, Default, PartialEq)]
+pub struct SpriteAnimation {
+    pub frames: Vec<Rect>,
+    /// Frames shown per second
+    pub fps: f32,
+    pub mode: AnimationMode,
+    /// The time the animation has been playing for.
+    pub elapsed: Duration,
+    /// Whether the animation advances, set to `false` to pause it on its current frame.
+    pub playing: bool,
+    /// How fast the animation plays, `1.0` for its normal speed
+    pub speed: f32,
+    /// Plays this clip instead of `frames`, with its frame durations and mode.
+    pub clip: Option<Handle<SpriteAnimationClip>>,
+    /// Plays only the frames of this tag of `clip`, or all of them if `None`.
+    pub tag: Option<String>,
+    /// The index of the frame last shown by [`animate_sprites`], `None` until the first one is
+    /// shown. Animations replacing another start with `None`, so their first frame is always sent
+    /// as an [`AnimationFrameEvent`].
+    pub shown_frame: Option<usize>,
+}
+
+impl Default for SpriteAnimation {
+    fn default() -> Self {
+        Self::new(Vec::new(), 12.0)
+    }
+}
+
+impl SpriteAnimation {
+    pub fn new(frames: impl Into<Vec<Rect>>, fps: f32) -> Self {
+        Self {
+            frames: frames.into(),
+            fps,
+            mode: AnimationMode::Loop,
+            elapsed: Duration::ZERO,
+            playing: true,
+            speed: 1.0,
+            clip: None,
+            tag: None,
+            shown_frame: None,
+        }
+    }
+
+    /// Creates an animation playing `clip`.
+    pub fn from_clip(clip: Handle<SpriteAnimationClip>) -> Self {
+        Self {
+            clip: Some(clip),
+            ..Self::new(Vec::new(), 0.0)
+        }
+    }
+
+    /// Creates an animation through the cells of a sprite sheet with `columns` × `rows` cells of
+    /// `cell_size` pixels, row by row from the top left.
+    pub fn from_grid(cell_size: UVec2, columns: u32, rows: u32, fps: f32) -> Self {
+        let cell_size = cell_size.as_vec2();
+        let frames: Vec<_> = (0..rows)
+            .flat_map(|row| (0..columns).map(move |column| UVec2::new(column, row)))
+            .map(|cell| {
+                let min = cell.as_vec2() * cell_size;
+                Rect::from_corners(min, min + cell_size)
+            })
+            .collect();
+        Self::new(frames, fps)
+    }
+
+    pub fn with_mode(mut self, mode: AnimationMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    pub fn with_speed(mut self, speed: f32) -> Self {
+        self.speed = speed;
+        self
+    }
+
+    /// Plays the frames of `tag` of the clip from the start.
+    pub fn play_tag(&mut self, tag: impl Into<String>) {
+        self.tag = Some(tag.into());
+        self.elapsed = Duration::ZERO;
+        self.playing = true;
+        self.shown_frame = None;
+    }
+
+    /// The index in `frames` of the frame shown at `elapsed`, `None` if there are no frames.
+    ///
+    /// Animations playing a clip always return `None`, see [`SpriteAnimationClip::frame_index`].
+    pub fn frame_index(&self) -> Option<usize> {
+        self.frame_at().map(|(index, _)| index)
+    }
+
+    /// Returns `true` once an animation played once is finished, see [`AnimationMode`].
+    ///
+    /// Animations playing a clip always return `false`, see [`SpriteAnimationClip::is_finished`].
+    pub fn is_finished(&self) -> bool {
+        self.frame_at().is_some_and(|(_, finished)| finished)
+    }
+
+    fn frame_at(&self) -> Option<(usize, bool)> {
+        if self.clip.is_some() {
+            return None;
+        }
+        let duration = 1.0 / self.fps.max(f32::EPSILON);
+        frame_at(0..self.frames.len(), |_| duration, self.mode, self.elapsed)
+    }
+
+    /// The rect of the frame shown at `elapsed`, `None` if there are no frames or the animation
+    /// plays a clip.
+    pub fn current_frame(&self) -> Option<Rect> {
+        self.frame_index().map(|index| self.frames[index])
+    }
+}
+
+/// How a [`SpriteAnimation`] continues after its last frame.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Reflect, Serialize, Deserialize)]
+#[reflect(Default, PartialEq)]
+pub enum AnimationMode {
+    /// Stop on the last frame and keep showing it. The animation is finished once the last frame
+    /// is shown.
+    OnceThenHold,
+    /// Despawn the entity and its descendants after showing the last frame, e.g. for effects like
+    /// explosions. The animation is finished once the last frame was shown for its duration.
+    OnceThenDespawn,
+    /// Start over from the first frame.
+    #[default]
+    Loop,
+    /// Play the frames backwards to the first one, then forwards again.
+    PingPong,
+}
+
+impl AnimationMode {
+    /// Returns `true` for the modes playing the frames once.
+    pub fn is_once(self) -> bool {
+        matches!(self, Self::OnceThenHold | Self::OnceThenDespawn)
+    }
+}
+
+/// Animation data played by a [`SpriteAnimation`], usually loaded from a `.anim.ron` file by
+/// [`SpriteAnimationClipLoader`]:
+///
+/// ```ron
+/// (
+///     frames: [
+///         (rect: (min: (0.0, 0.0), max: (16.0, 16.0))),
+///         (rect: (min: (16.0, 0.0), max: (32.0, 16.0)), duration: Some(0.25)),
+///     ],
+///     frame_duration: 0.1,
+///     mode: Loop,
+///     tags: {
+///         "idle": (start: 0, end: 1),
+///     },
+/// )
+/// ```
+///
+/// Modifying the clip, e.g. through hot reloading, takes effect on every animation playing it.
+#[derive(Asset, TypePath, Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SpriteAnimationClip {
+    pub frames: Vec<AnimationFrame>,
+    /// The time in seconds frames without their own duration are shown for
+    #[serde(default = "default_frame_duration")]
+    pub frame_duration: f32,
+    #[serde(default)]
+    pub mode: AnimationMode,
+    /// Named ranges of `frames`, such as `"run"` or `"attack"`, see [`SpriteAnimation::tag`]
+    #[serde(default)]
+    pub tags: HashMap<String, Range<usize>>,
+}
+
+fn default_frame_duration() -> f32 {
+    0.1
+}
+
+/// A frame of a [`SpriteAnimationClip`].
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct AnimationFrame {
+    pub rect: Rect,
+    /// The time in seconds the frame is shown for, instead of the clip's `frame_duration`
+    #[serde(default)]
+    pub duration: Option<f32>,
+}
+
+impl SpriteAnimationClip {
+    /// The frames of `tag`, or of the whole clip if `tag` is `None` or unknown.
+    pub fn tag_range(&self, tag: Option<&str>) -> Range<usize> {
+        let all = 0..self.frames.len();
+        match tag.and_then(|tag| self.tags.get(tag)) {
+            Some(range) => range.start.min(all.end)..range.end.min(all.end),
+            None => all,
+        }
+    }
+
+    /// The index in `frames` of the frame shown `elapsed` into playing `tag`, `None` if it has no
+    /// frames.
+    pub fn frame_index(&self, tag: Option<&str>, elapsed: Duration) -> Option<usize> {
+        self.frame_at(tag, elapsed).map(|(index, _)| index)
+    }
+
+    /// Returns `true` once a clip played once is finished with the frames of `tag`, see
+    /// [`AnimationMode`].
+    pub fn is_finished(&self, tag: Option<&str>, elapsed: Duration) -> bool {
+        self.frame_at(tag, elapsed)
+            .is_some_and(|(_, finished)| finished)
+    }
+
+    fn frame_at(&self, tag: Option<&str>, elapsed: Duration) -> Option<(usize, bool)> {
+        frame_at(
+            self.tag_range(tag),
+            |index| self.frames[index].duration.unwrap_or(self.frame_duration),
+            self.mode,
+            elapsed,
+        )
+    }
+}
+
+/// The frame of `frames` shown `elapsed` into the animation, and whether an animation played once
+/// is finished.
+fn frame_at(
+    frames: Range<usize>,
+    duration: impl Fn(usize) -> f32,
+    mode: AnimationMode,
+    elapsed: Duration,
+) -> Option<(usize, bool)> {
+    if frames.is_empty() {
+        return None;
+    }
+    let last = frames.end - 1;
+    let duration = |index| duration(index).max(0.0);
+    let total: f32 = frames.clone().map(duration).sum();
+    if total <= 0.0 {
+        return Some((frames.start, mode.is_once()));
+    }
+
+    let elapsed = elapsed.as_secs_f32();
+    // Walks the frames in `order` until `time` falls within one
+    let find = |mut time: f32, order: &mut dyn Iterator<Item = usize>| {
+        for index in order {
+            time -= duration(index);
+            if time < 0.0 {
+                return index;
+            }
+        }
+        last
+    };
+    let index = match mode {
+        AnimationMode::OnceThenHold => {
+            let index = find(elapsed, &mut frames.clone());
+            return Some((index, elapsed >= total - duration(last)));
+        }
+        AnimationMode::OnceThenDespawn => {
+            let index = find(elapsed, &mut frames.clone());
+            return Some((index, elapsed >= total));
+        }
+        AnimationMode::Loop => find(elapsed % total, &mut frames.clone()),
+        AnimationMode::PingPong => {
+            let inner = frames.start + 1..last;
+            let cycle = total + inner.clone().map(duration).sum::<f32>();
+            find(elapsed % cycle, &mut frames.clone().chain(inner.rev()))
+        }
+    };
+    Some((index, false))
+}
+
+/// Errors of [`SpriteAnimationClipLoader`].
+#[non_exhaustive]
+#[derive(Debug, Error)]
+pub enum SpriteAnimationClipLoaderError {
+    #[error("Could not read animation clip: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Could not parse animation clip: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+}
+
+/// [`AssetLoader`] reading [`SpriteAnimationClip`]s from RON files with the `.anim.ron` extension.
+#[derive(Default)]
+pub struct SpriteAnimationClipLoader;
+
+impl AssetLoader for SpriteAnimationClipLoader {
+    type Asset = SpriteAnimationClip;
+    type Settings = ();
+    type Error = SpriteAnimationClipLoaderError;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a Self::Settings,
+        _load_context: &'a mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        Ok(ron::de::from_bytes(&bytes)?)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["anim.ron"]
+    }
+}
+
+/// Sent by [`animate_sprites`] when a [`SpriteAnimation`] shows a new frame, including the first
+/// frame of a new animation.
+///
+/// When an update advances an animation past several frames, only the frame it ends on is sent.
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AnimationFrameEvent {
+    pub entity: Entity,
+    /// The clip the frame belongs to, `None` for the frames of [`SpriteAnimation::frames`]
+    pub clip: Option<AssetId<SpriteAnimationClip>>,
+    /// The index of the frame in the frames of the animation or clip
+    pub frame: usize,
+}
+
+/// Sent by [`animate_sprites`] when an animation played once is finished, see [`AnimationMode`].
+///
+/// With [`AnimationMode::OnceThenDespawn`], the entity is despawned right after the event is sent.
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AnimationFinishedEvent {
+    pub entity: Entity,
+    pub clip: Option<AssetId<SpriteAnimationClip>>,
+}
+
+/// System advancing [`SpriteAnimation`]s and showing their current frame.
+pub fn animate_sprites(
+    mut commands: Commands,
+    time: Res<Time>,
+    clips: Res<Assets<SpriteAnimationClip>>,
+    mut sprites: Query<(Entity, &mut SpriteAnimation, &mut SpriteEx)>,
+    mut frame_events: EventWriter<AnimationFrameEvent>,
+    mut finished_events: EventWriter<AnimationFinishedEvent>,
+) {
+    for (entity, mut animation, mut sprite) in &mut sprites {
+        let clip = match animation.clip.as_ref().map(Handle::id) {
+            Some(id) => match clips.get(id) {
+                Some(clip) => Some((id, clip)),
+                // Clips that aren't loaded yet keep the animation at its start
+                None => continue,
+            },
+            None => None,
+        };
+        let frame_at = |animation: &SpriteAnimation| match clip {
+            Some((_, clip)) => clip.frame_at(animation.tag.as_deref(), animation.elapsed),
+            None => animation.frame_at(),
+        };
+
+        let previous = frame_at(&animation);
+        if animation.playing && !previous.is_some_and(|(_, finished)| finished) {
+            let delta = time.delta().mul_f32(animation.speed.max(0.0));
+            animation.elapsed += delta;
+        }
+        let Some((index, finished)) = frame_at(&animation) else {
+            continue;
+        };
+
+        // Animations replaced in place, e.g. by a state change, haven't shown a frame yet
+        let started = animation.is_added() || animation.shown_frame.is_none();
+        let clip_id = clip.map(|(id, _)| id);
+        if started || animation.shown_frame != Some(index) {
+            animation.shown_frame = Some(index);
+            frame_events.send(AnimationFrameEvent {
+                entity,
+                clip: clip_id,
+                frame: index,
+            });
+        }
+        if finished && (started || !previous.is_some_and(|(_, finished)| finished)) {
+            finished_events.send(AnimationFinishedEvent {
+                entity,
+                clip: clip_id,
+            });
+            let mode = clip.map_or(animation.mode, |(_, clip)| clip.mode);
+            if mode == AnimationMode::OnceThenDespawn {
+                commands.entity(entity).despawn_recursive();
+                continue;
+            }
+        }
+
+        let rect = match clip {
+            Some((_, clip)) => clip.frames[index].rect,
+            None => animation.frames[index],
+        };
+        if sprite.rect != Some(rect) {
+            sprite.rect = Some(rect);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An animation of four frames shown for 100 ms each.
+    fn animation(mode: AnimationMode) -> SpriteAnimation {
+        SpriteAnimation::from_grid(UVec2::splat(16), 4, 1, 10.0).with_mode(mode)
+    }
+
+    /// The frame and whether the animation is finished at each of `millis`, sampled in the middle
+    /// of frames to stay clear of rounding at their boundaries.
+    fn frames_at(mut animation: SpriteAnimation, millis: &[u64]) -> Vec<(usize, bool)> {
+        millis
+            .iter()
+            .map(|&millis| {
+                animation.elapsed = Duration::from_millis(millis);
+                (animation.frame_index().unwrap(), animation.is_finished())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn loop_starts_over() {
+        assert_eq!(
+            frames_at(animation(AnimationMode::Loop), &[50, 150, 350, 450, 1250]),
+            [(0, false), (1, false), (3, false), (0, false), (0, false)]
+        );
+    }
+
+    #[test]
+    fn ping_pong_reverses_without_repeating_ends() {
+        let millis: Vec<u64> = (0..8).map(|frame| frame * 100 + 50).collect();
+        let frames: Vec<usize> = frames_at(animation(AnimationMode::PingPong), &millis)
+            .into_iter()
+            .map(|(frame, _)| frame)
+            .collect();
+        assert_eq!(frames, [0, 1, 2, 3, 2, 1, 0, 1]);
+    }
+
+    #[test]
+    fn once_then_hold_finishes_on_last_frame() {
+        assert_eq!(
+            frames_at(animation(AnimationMode::OnceThenHold), &[250, 350, 5000]),
+            [(2, false), (3, true), (3, true)]
+        );
+    }
+
+    #[test]
+    fn once_then_despawn_finishes_after_last_frame() {
+        assert_eq!(
+            frames_at(animation(AnimationMode::OnceThenDespawn), &[350, 450]),
+            [(3, false), (3, true)]
+        );
+    }
+
+    #[test]
+    fn no_frames() {
+        let animation = SpriteAnimation::new(Vec::new(), 10.0);
+        assert_eq!(animation.frame_index(), None);
+        assert!(!animation.is_finished());
+    }
+
+    #[test]
+    fn clip_frame_durations_and_tags() {
+        let frame = |duration| AnimationFrame {
+            rect: Rect::default(),
+            duration,
+        };
+        let clip = SpriteAnimationClip {
+            frames: vec![frame(None), frame(Some(0.3)), frame(None)],
+            frame_duration: 0.1,
+            mode: AnimationMode::Loop,
+            tags: [("tail".to_string(), 1..3)].into_iter().collect(),
+        };
+        let index = |tag, millis| clip.frame_index(tag, Duration::from_millis(millis));
+        assert_eq!(index(None, 50), Some(0));
+        assert_eq!(index(None, 350), Some(1));
+        assert_eq!(index(None, 450), Some(2));
+        assert_eq!(index(Some("tail"), 350), Some(2));
+        assert_eq!(index(Some("tail"), 450), Some(1));
+        assert_eq!(index(Some("unknown"), 450), Some(2));
+    }
+
+    #[test]
+    fn replaced_animation_sends_its_first_frame() {
+        let mut world = World::new();
+        world.init_resource::<Time>();
+        world.init_resource::<Assets<SpriteAnimationClip>>();
+        world.init_resource::<Events<AnimationFrameEvent>>();
+        world.init_resource::<Events<AnimationFinishedEvent>>();
+        let entity = world
+            .spawn((animation(AnimationMode::Loop), SpriteEx::default()))
+            .id();
+        let mut schedule = Schedule::default();
+        schedule.add_systems(animate_sprites);
+        let mut frames = |world: &mut World| {
+            schedule.run(world);
+            let mut events = world.resource_mut::<Events<AnimationFrameEvent>>();
+            let frames: Vec<usize> = events.drain().map(|event| event.frame).collect();
+            frames
+        };
+        assert_eq!(frames(&mut world), [0]);
+        assert_eq!(frames(&mut world), []);
+
+        // Replacing the animation in place, like a state change, starts on the same frame index
+        *world.get_mut::<SpriteAnimation>(entity).unwrap() = animation(AnimationMode::PingPong);
+        assert_eq!(frames(&mut world), [0]);
+    }
+}
@@ -0,0 +1,175 @@
+use bevy_asset::Assets;
+use bevy_ecs::prelude::*;
+use bevy_reflect::{std_traits::ReflectDefault, Reflect};
+use bevy_utils::HashMap;
+
+use crate::{SpriteAnimation, SpriteAnimationClip};
+
+/// Switches the [`SpriteAnimation`] of an entity between named states, e.g. `"idle"`, `"run"` and
+/// `"attack"` of a character.
+///
+/// Each state is the animation played while in it. [`update_animation_state_machines`] takes the
+/// first transition from the current state whose conditions all hold, replacing the entity's
+/// [`SpriteAnimation`] with the one of the new state without blending. The animation of the
+/// current state is also inserted when the state machine is added.
+#[derive(Component, Debug, Default, Clone, PartialEq, Reflect)]
+#[reflect(Component, Default, PartialEq)]
+pub struct SpriteAnimationStateMachine {
+    pub states: HashMap<String, SpriteAnimation>,
+    pub transitions: Vec<AnimationTransition>,
+    /// The name of the current state
+    pub current: String,
+    /// The parameters conditions are evaluated against, set by gameplay code
+    pub params: HashMap<String, f32>,
+}
+
+impl SpriteAnimationStateMachine {
+    /// Creates a state machine starting in the state `initial`.
+    pub fn new(initial: impl Into<String>) -> Self {
+        Self {
+            current: initial.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_state(mut self, name: impl Into<String>, animation: SpriteAnimation) -> Self {
+        self.states.insert(name.into(), animation);
+        self
+    }
+
+    pub fn with_transition(mut self, transition: AnimationTransition) -> Self {
+        self.transitions.push(transition);
+        self
+    }
+
+    /// Sets the parameter `name`.
+    pub fn set_param(&mut self, name: impl Into<String>, value: f32) {
+        self.params.insert(name.into(), value);
+    }
+
+    /// Sets the parameter `name` to `1.0` if `value` is `true` and `0.0` otherwise.
+    pub fn set_bool(&mut self, name: impl Into<String>, value: bool) {
+        self.set_param(name, if value { 1.0 } else { 0.0 });
+    }
+
+    /// The value of the parameter `name`, `0.0` if it isn't set.
+    pub fn param(&self, name: &str) -> f32 {
+        self.params.get(name).copied().unwrap_or_default()
+    }
+}
+
+/// A transition of a [`SpriteAnimationStateMachine`].
+#[derive(Debug, Default, Clone, PartialEq, Reflect)]
+#[reflect(Default, PartialEq)]
+pub struct AnimationTransition {
+    /// The state the transition is taken from, `None` to take it from any other state
+    pub from: Option<String>,
+    pub to: String,
+    /// Conditions that all need to hold for the transition to be taken
+    pub conditions: Vec<AnimationCondition>,
+}
+
+impl AnimationTransition {
+    pub fn new(from: impl Into<String>, to: impl Into<String>) -> Self {
+        Self {
+            from: Some(from.into()),
+            to: to.into(),
+            conditions: Vec::new(),
+        }
+    }
+
+    /// Creates a transition to `to` from any other state.
+    pub fn from_any(to: impl Into<String>) -> Self {
+        Self {
+            from: None,
+            to: to.into(),
+            conditions: Vec::new(),
+        }
+    }
+
+    pub fn when(mut self, condition: AnimationCondition) -> Self {
+        self.conditions.push(condition);
+        self
+    }
+}
+
+/// A condition of an [`AnimationTransition`].
+#[derive(Debug, Clone, PartialEq, Reflect)]
+pub enum AnimationCondition {
+    /// The parameter is non-zero, e.g. set to `true` with
+    /// [`SpriteAnimationStateMachine::set_bool`].
+    IsTrue(String),
+    /// The parameter is zero.
+    IsFalse(String),
+    /// The parameter is greater than the value.
+    Greater(String, f32),
+    /// The parameter is less than the value.
+    Less(String, f32),
+    /// The animation of the current state is finished, see [`SpriteAnimation::is_finished`].
+    Finished,
+}
+
+impl AnimationCondition {
+    fn holds(&self, machine: &SpriteAnimationStateMachine, finished: bool) -> bool {
+        match self {
+            Self::IsTrue(name) => machine.param(name) != 0.0,
+            Self::IsFalse(name) => machine.param(name) == 0.0,
+            Self::Greater(name, value) => machine.param(name) > *value,
+            Self::Less(name, value) => machine.param(name) < *value,
+            Self::Finished => finished,
+        }
+    }
+}
+
+/// System switching the [`SpriteAnimation`]s of entities with a [`SpriteAnimationStateMachine`]
+/// to the state of the first transition whose conditions hold.
+pub fn update_animation_state_machines(
+    mut commands: Commands,
+    clips: Res<Assets<SpriteAnimationClip>>,
+    mut machines: Query<(
+        Entity,
+        &mut SpriteAnimationStateMachine,
+        Option<&mut SpriteAnimation>,
+    )>,
+) {
+    for (entity, mut machine, animation) in &mut machines {
+        let finished = animation
+            .as_deref()
+            .is_some_and(|animation| match &animation.clip {
+                Some(clip) => clips.get(clip).is_some_and(|clip| {
+                    clip.is_finished(animation.tag.as_deref(), animation.elapsed)
+                }),
+                None => animation.is_finished(),
+            });
+
+        let transition = machine.transitions.iter().find(|transition| {
+            transition.to != machine.current
+                && transition
+                    .from
+                    .as_ref()
+                    .is_none_or(|from| *from == machine.current)
+                && transition
+                    .conditions
+                    .iter()
+                    .all(|condition| condition.holds(&machine, finished))
+        });
+        let state = match transition {
+            Some(transition) => transition.to.clone(),
+            None if machine.is_added() || animation.is_none() => machine.current.clone(),
+            None => continue,
+        };
+        let Some(state_animation) = machine.states.get(&state).cloned() else {
+            continue;
+        };
+
+        match animation {
+            Some(mut animation) => *animation = state_animation,
+            None => {
+                commands.entity(entity).insert(state_animation);
+            }
+        }
+        if machine.current != state {
+            machine.current = state;
+        }
+    }
+}
@@ -0,0 +1,95 @@
+use bevy_app::prelude::*;
+use bevy_asset::{Assets, Handle};
+use bevy_color::{palettes::css, Color};
+use bevy_ecs::prelude::*;
+use bevy_gizmos::gizmos::Gizmos;
+use bevy_math::Vec2;
+use bevy_reflect::{std_traits::ReflectDefault, Reflect};
+use bevy_render::{
+    texture::Image,
+    view::{ViewVisibility, VisibilitySystems},
+};
+use bevy_transform::components::GlobalTransform;
+
+use crate::{ScissorRect, SpriteEx};
+
+/// Draws the outline of the quad of every visible [`SpriteEx`] with gizmos, for debugging sizes,
+/// anchors and culling.
+///
+/// Needs the `debug` feature of this crate, and Bevy's `GizmoPlugin`, which is part of
+/// `DefaultPlugins` with the `bevy_gizmos` feature.
+/// The outlines are drawn where the sprites are in the main world, without their
+/// [`ExtractOffset`](crate::ExtractOffset).
+#[derive(Default)]
+pub struct SpriteExDebugPlugin;
+
+impl Plugin for SpriteExDebugPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<SpriteExDebugSettings>()
+            .init_resource::<SpriteExDebugSettings>()
+            .add_systems(
+                PostUpdate,
+                draw_sprite_debug_gizmos
+                    .after(VisibilitySystems::CheckVisibility)
+                    .run_if(|settings: Res<SpriteExDebugSettings>| settings.enabled),
+            );
+    }
+}
+
+/// Settings of the [`SpriteExDebugPlugin`].
+#[derive(Resource, Debug, Clone, Reflect)]
+#[reflect(Resource, Default)]
+pub struct SpriteExDebugSettings {
+    pub enabled: bool,
+    /// The color of the outlines of sprites
+    pub color: Color,
+    /// The color of the outlines of sprites clipped by a [`ScissorRect`]
+    pub scissored_color: Color,
+}
+
+impl Default for SpriteExDebugSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            color: css::LIME.into(),
+            scissored_color: css::ORANGE.into(),
+        }
+    }
+}
+
+/// System drawing the outlines of the [`SpriteExDebugPlugin`].
+pub fn draw_sprite_debug_gizmos(
+    mut gizmos: Gizmos,
+    settings: Res<SpriteExDebugSettings>,
+    images: Res<Assets<Image>>,
+    sprites: Query<(
+        &SpriteEx,
+        &Handle<Image>,
+        &GlobalTransform,
+        &ViewVisibility,
+        Has<ScissorRect>,
+    )>,
+) {
+    for (sprite, image, transform, view_visibility, scissored) in &sprites {
+        if !view_visibility.get() {
+            continue;
+        }
+        let Some(size) = sprite.quad_size(images.get(image)) else {
+            continue;
+        };
+        let center = -sprite.flipped_anchor() * size;
+        let corners = [
+            Vec2::new(-0.5, -0.5),
+            Vec2::new(0.5, -0.5),
+            Vec2::new(0.5, 0.5),
+            Vec2::new(-0.5, 0.5),
+            Vec2::new(-0.5, -0.5),
+        ]
+        .map(|corner| transform.transform_point((center + corner * size).extend(0.0)));
+        let color = match scissored {
+            true => settings.scissored_color,
+            false => settings.color,
+        };
+        gizmos.linestrip(corners, color);
+    }
+}
@@ -0,0 +1,88 @@
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+};
+
+use bevy_app::prelude::*;
+use bevy_diagnostic::{Diagnostic, DiagnosticPath, Diagnostics, RegisterDiagnostic};
+use bevy_ecs::prelude::*;
+use bevy_render::{Render, RenderApp, RenderSet};
+
+use crate::{ExtractedSprites, SpriteBatch, SpriteMeta};
+
+/// Adds diagnostics of the sprites rendered each frame to the `DiagnosticsStore`, e.g. to print
+/// them with Bevy's `LogDiagnosticsPlugin`.
+///
+/// The measurements are read from the render world, so they describe the frame rendered during
+/// the previous update. Add it after [`SpriteExPlugin`](crate::SpriteExPlugin). Needs the
+/// `diagnostics` feature.
+#[derive(Default)]
+pub struct SpriteExDiagnosticsPlugin;
+
+impl SpriteExDiagnosticsPlugin {
+    /// The number of extracted sprites, including derived sprites such as slices
+    pub const EXTRACTED_SPRITES: DiagnosticPath =
+        DiagnosticPath::const_new("sprite_ex/extracted_sprites");
+    /// The number of sprite batches, each drawn with one draw call
+    pub const BATCHES: DiagnosticPath = DiagnosticPath::const_new("sprite_ex/batches");
+    /// The size of the instances written to the sprite instance buffer
+    pub const INSTANCE_BYTES: DiagnosticPath =
+        DiagnosticPath::const_new("sprite_ex/instance_bytes");
+}
+
+impl Plugin for SpriteExDiagnosticsPlugin {
+    fn build(&self, app: &mut App) {
+        let stats = SpriteRenderStats::default();
+        app.insert_resource(stats.clone())
+            .register_diagnostic(Diagnostic::new(Self::EXTRACTED_SPRITES))
+            .register_diagnostic(Diagnostic::new(Self::BATCHES))
+            .register_diagnostic(Diagnostic::new(Self::INSTANCE_BYTES).with_suffix(" B"))
+            .add_systems(Update, measure_sprite_diagnostics);
+
+        if let Some(render_app) = app.get_sub_app_mut(RenderApp) {
+            render_app
+                .insert_resource(stats)
+                .add_systems(Render, record_sprite_render_stats.in_set(RenderSet::Render));
+        }
+    }
+}
+
+/// Counts written by the render world for the [`SpriteExDiagnosticsPlugin`], shared with the main
+/// world.
+#[derive(Resource, Debug, Default, Clone)]
+pub struct SpriteRenderStats {
+    extracted_sprites: Arc<AtomicU64>,
+    batches: Arc<AtomicU64>,
+    instance_bytes: Arc<AtomicU64>,
+}
+
+/// System recording the [`SpriteRenderStats`] of the frame once it is prepared.
+pub fn record_sprite_render_stats(
+    stats: Res<SpriteRenderStats>,
+    extracted_sprites: Res<ExtractedSprites>,
+    sprite_meta: Res<SpriteMeta>,
+    batches: Query<(), With<SpriteBatch>>,
+) {
+    let instance_bytes = std::mem::size_of_val(sprite_meta.instances());
+    stats
+        .extracted_sprites
+        .store(extracted_sprites.sprites.len() as u64, Ordering::Relaxed);
+    stats
+        .batches
+        .store(batches.iter().count() as u64, Ordering::Relaxed);
+    stats
+        .instance_bytes
+        .store(instance_bytes as u64, Ordering::Relaxed);
+}
+
+/// System adding the [`SpriteRenderStats`] as measurements of the [`SpriteExDiagnosticsPlugin`].
+pub fn measure_sprite_diagnostics(stats: Res<SpriteRenderStats>, mut diagnostics: Diagnostics) {
+    let load = |value: &AtomicU64| value.load(Ordering::Relaxed) as f64;
+    diagnostics.add_measurement(&SpriteExDiagnosticsPlugin::EXTRACTED_SPRITES, || {
+        load(&stats.extracted_sprites)
+    });
+    diagnostics.add_measurement(&SpriteExDiagnosticsPlugin::BATCHES, || load(&stats.batches));
+    diagnostics.add_measurement(&SpriteExDiagnosticsPlugin::INSTANCE_BYTES, || {
+        load(&stats.instance_bytes)
+    });
+}
@@ -0,0 +1,170 @@
+use bevy_ecs::prelude::*;
+use bevy_hierarchy::DespawnRecursiveExt;
+use bevy_reflect::{std_traits::ReflectDefault, Reflect};
+use bevy_render::view::Visibility;
+use bevy_time::Time;
+use bevy_utils::Duration;
+
+/// Fades a sprite in from transparent over `duration`, then removes itself.
+///
+/// The fade multiplies the alpha of the sprite's color when it is extracted for rendering, the
+/// [`SpriteEx::color`](crate::SpriteEx::color) itself is left untouched.
+#[derive(Component, Debug, Default, Clone, Copy, PartialEq, Reflect)]
+#[reflect(Component, Default, PartialEq)]
+pub struct FadeIn {
+    pub duration: Duration,
+    /// The time the fade has been running for.
+    pub elapsed: Duration,
+}
+
+impl FadeIn {
+    pub fn new(duration: Duration) -> Self {
+        Self {
+            duration,
+            elapsed: Duration::ZERO,
+        }
+    }
+
+    /// The opacity the sprite is drawn with.
+    pub fn opacity(&self) -> f32 {
+        fade_progress(self.elapsed, self.duration)
+    }
+}
+
+/// Fades a sprite out to transparent over `duration`, then applies `on_end`.
+///
+/// Like [`FadeIn`], the fade only affects the color the sprite is drawn with.
+#[derive(Component, Debug, Default, Clone, Copy, PartialEq, Reflect)]
+#[reflect(Component, Default, PartialEq)]
+pub struct FadeOut {
+    pub duration: Duration,
+    /// The time the fade has been running for.
+    pub elapsed: Duration,
+    pub on_end: FadeOutEnd,
+}
+
+impl FadeOut {
+    pub fn new(duration: Duration) -> Self {
+        Self {
+            duration,
+            elapsed: Duration::ZERO,
+            on_end: FadeOutEnd::Keep,
+        }
+    }
+
+    pub fn with_on_end(mut self, on_end: FadeOutEnd) -> Self {
+        self.on_end = on_end;
+        self
+    }
+
+    /// The opacity the sprite is drawn with.
+    pub fn opacity(&self) -> f32 {
+        1.0 - fade_progress(self.elapsed, self.duration)
+    }
+}
+
+/// What happens to an entity once its [`FadeOut`] completes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Reflect)]
+#[reflect(Default, PartialEq)]
+pub enum FadeOutEnd {
+    /// Keep the [`FadeOut`] component, leaving the sprite fully transparent.
+    #[default]
+    Keep,
+    /// Remove the [`FadeOut`] component and set the entity's [`Visibility`] to hidden.
+    Hide,
+    /// Despawn the entity and its descendants.
+    Despawn,
+}
+
+fn fade_progress(elapsed: Duration, duration: Duration) -> f32 {
+    if duration.is_zero() {
+        1.0
+    } else {
+        (elapsed.as_secs_f32() / duration.as_secs_f32()).min(1.0)
+    }
+}
+
+/// System advancing [`FadeIn`] and [`FadeOut`] components, and applying them once they end.
+pub fn update_fades(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut fade_ins: Query<(Entity, &mut FadeIn)>,
+    mut fade_outs: Query<(Entity, &mut FadeOut, Option<&mut Visibility>)>,
+) {
+    for (entity, mut fade_in) in &mut fade_ins {
+        fade_in.elapsed += time.delta();
+        if fade_in.elapsed >= fade_in.duration {
+            commands.entity(entity).remove::<FadeIn>();
+        }
+    }
+
+    for (entity, mut fade_out, visibility) in &mut fade_outs {
+        // Kept fades stop advancing at their duration
+        if fade_out.elapsed < fade_out.duration {
+            fade_out.elapsed = (fade_out.elapsed + time.delta()).min(fade_out.duration);
+        }
+        if fade_out.elapsed < fade_out.duration {
+            continue;
+        }
+        match fade_out.on_end {
+            FadeOutEnd::Keep => {}
+            FadeOutEnd::Hide => {
+                if let Some(mut visibility) = visibility {
+                    *visibility = Visibility::Hidden;
+                }
+                commands.entity(entity).remove::<FadeOut>();
+            }
+            FadeOutEnd::Despawn => commands.entity(entity).despawn_recursive(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_ecs::system::RunSystemOnce;
+
+    use super::*;
+
+    fn world() -> World {
+        let mut world = World::new();
+        world.init_resource::<Time>();
+        world
+    }
+
+    #[test]
+    fn zero_duration_fade_out_hides() {
+        let mut world = world();
+        let entity = world
+            .spawn((
+                FadeOut::new(Duration::ZERO).with_on_end(FadeOutEnd::Hide),
+                Visibility::Visible,
+            ))
+            .id();
+        world.run_system_once(update_fades);
+        assert_eq!(world.get::<Visibility>(entity), Some(&Visibility::Hidden));
+        assert!(world.get::<FadeOut>(entity).is_none());
+    }
+
+    #[test]
+    fn zero_duration_fade_out_despawns() {
+        let mut world = world();
+        let entity = world
+            .spawn(FadeOut::new(Duration::ZERO).with_on_end(FadeOutEnd::Despawn))
+            .id();
+        world.run_system_once(update_fades);
+        assert!(world.get_entity(entity).is_none());
+    }
+
+    #[test]
+    fn kept_fade_out_stops_at_its_duration() {
+        let mut world = world();
+        let entity = world.spawn(FadeOut::new(Duration::from_millis(250))).id();
+        world
+            .resource_mut::<Time>()
+            .advance_by(Duration::from_secs(1));
+        world.run_system_once(update_fades);
+        let fade_out = world.get::<FadeOut>(entity).unwrap();
+        assert_eq!(fade_out.elapsed, Duration::from_millis(250));
+        assert_eq!(fade_out.opacity(), 0.0);
+    }
+}
@@ -0,0 +1,97 @@
+use std::{
+    hash::{BuildHasher, Hash, Hasher},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+use bevy_core_pipeline::core_2d::Transparent2d;
+use bevy_ecs::prelude::*;
+use bevy_render::{
+    render_phase::ViewSortedRenderPhases,
+    render_resource::{PipelineCache, RenderPipelineDescriptor},
+};
+use bevy_utils::FixedState;
+
+use crate::{SpriteBatch, SpriteMeta};
+
+/// Hash of the sprite instances and batches prepared for the last rendered frame.
+///
+/// Enabled with [`SpriteExPlugin::frame_hash`]. Integration tests can compare it between runs to
+/// check that changes to extraction, queueing or preparation produce the same render input,
+/// without comparing rendered images. The hash covers the bytes of every instance and, for each
+/// view in entity order, the specialized pipeline, image, dissolve noise, scissor, winding and
+/// instance range of each batch in draw order. It is `0` until a frame is rendered, and only
+/// stable within the same build of the crate.
+///
+/// [`SpriteExPlugin::frame_hash`]: crate::SpriteExPlugin::frame_hash
+#[derive(Resource, Debug, Default, Clone)]
+pub struct SpriteFrameHash {
+    hash: Arc<AtomicU64>,
+}
+
+impl SpriteFrameHash {
+    /// The hash of the last rendered frame.
+    pub fn get(&self) -> u64 {
+        self.hash.load(Ordering::Relaxed)
+    }
+}
+
+/// System hashing the prepared [`SpriteMeta`] instances and [`SpriteBatch`]es into the
+/// [`SpriteFrameHash`].
+pub fn hash_sprite_frame(
+    frame_hash: Res<SpriteFrameHash>,
+    sprite_meta: Res<SpriteMeta>,
+    transparent_render_phases: Res<ViewSortedRenderPhases<Transparent2d>>,
+    pipeline_cache: Res<PipelineCache>,
+    batches: Query<&SpriteBatch>,
+) {
+    let mut hasher = FixedState.build_hasher();
+    hasher.write(bytemuck::cast_slice(sprite_meta.instances()));
+
+    let mut views: Vec<_> = transparent_render_phases.iter().collect();
+    views.sort_unstable_by_key(|(view, _)| **view);
+    for (_, phase) in views {
+        for item in &phase.items {
+            let Ok(batch) = batches.get(item.entity) else {
+                continue;
+            };
+            // The descriptor rather than the pipeline ID, which depends on the order pipelines
+            // are specialized in
+            hash_pipeline(
+                pipeline_cache.get_render_pipeline_descriptor(item.pipeline),
+                &mut hasher,
+            );
+            batch.image_handle_id.hash(&mut hasher);
+            batch.scissor.hash(&mut hasher);
+            batch.mirrored.hash(&mut hasher);
+            batch.sampler.hash(&mut hasher);
+            batch.dissolve_noise.hash(&mut hasher);
+            batch.texture_array.hash(&mut hasher);
+            batch.range.hash(&mut hasher);
+        }
+    }
+
+    frame_hash.hash.store(hasher.finish(), Ordering::Relaxed);
+}
+
+/// Hashes the shaders, shader defs and fixed function state of a specialized pipeline, which
+/// include the blend state of its color targets.
+fn hash_pipeline(descriptor: &RenderPipelineDescriptor, hasher: &mut impl Hasher) {
+    descriptor.label.hash(hasher);
+    let vertex = &descriptor.vertex;
+    vertex.shader.hash(hasher);
+    vertex.shader_defs.hash(hasher);
+    vertex.entry_point.hash(hasher);
+    vertex.buffers.hash(hasher);
+    descriptor.primitive.hash(hasher);
+    descriptor.depth_stencil.hash(hasher);
+    descriptor.multisample.hash(hasher);
+    if let Some(fragment) = &descriptor.fragment {
+        fragment.shader.hash(hasher);
+        fragment.shader_defs.hash(hasher);
+        fragment.entry_point.hash(hasher);
+        fragment.targets.hash(hasher);
+    }
+}
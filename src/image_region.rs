@@ -0,0 +1,118 @@
+use bevy_asset::AssetId;
+use bevy_ecs::prelude::*;
+use bevy_math::URect;
+use bevy_render::{
+    render_asset::RenderAssets,
+    render_resource::{Extent3d, ImageCopyTexture, ImageDataLayout, Origin3d, TextureAspect},
+    renderer::RenderQueue,
+    texture::{GpuImage, Image, TextureFormatPixelInfo},
+    MainWorld,
+};
+use bevy_utils::tracing::warn;
+
+/// Queue of writes to regions of images, uploaded to their GPU textures in place.
+///
+/// Modifying an [`Image`] through `Assets::get_mut` re-uploads the whole texture and recreates the
+/// bind groups of every sprite using it. For images updated often and in small parts, such as
+/// procedural canvases or minimaps, write only the changed regions here instead.
+///
+/// Only the GPU texture is written: the [`Image`] asset keeps its previous data, and modifying the
+/// asset later uploads that data again. Writes to images that aren't on the GPU yet are dropped.
+#[derive(Resource, Default)]
+pub struct ImageRegionWrites {
+    writes: Vec<ImageRegionWrite>,
+}
+
+struct ImageRegionWrite {
+    image: AssetId<Image>,
+    rect: URect,
+    data: Vec<u8>,
+}
+
+impl ImageRegionWrites {
+    /// Queues writing `data` to the texels of `rect` in `image`.
+    ///
+    /// `data` holds the rows of the region from top to bottom, tightly packed in the texture format
+    /// of the image.
+    pub fn write(
+        &mut self,
+        image: impl Into<AssetId<Image>>,
+        rect: URect,
+        data: impl Into<Vec<u8>>,
+    ) {
+        self.writes.push(ImageRegionWrite {
+            image: image.into(),
+            rect,
+            data: data.into(),
+        });
+    }
+
+    /// Returns `true` if no writes are queued.
+    pub fn is_empty(&self) -> bool {
+        self.writes.is_empty()
+    }
+}
+
+/// Moves the queued [`ImageRegionWrites`] of the main world into the render world.
+pub fn extract_image_region_writes(
+    mut main_world: ResMut<MainWorld>,
+    mut writes: ResMut<ImageRegionWrites>,
+) {
+    let Some(mut main_writes) = main_world.get_resource_mut::<ImageRegionWrites>() else {
+        return;
+    };
+    writes.writes.append(&mut main_writes.writes);
+}
+
+/// Uploads the extracted [`ImageRegionWrites`] to their textures.
+pub fn write_image_regions(
+    mut writes: ResMut<ImageRegionWrites>,
+    gpu_images: Res<RenderAssets<GpuImage>>,
+    render_queue: Res<RenderQueue>,
+) {
+    for write in writes.writes.drain(..) {
+        let Some(gpu_image) = gpu_images.get(write.image) else {
+            continue;
+        };
+
+        let size = write.rect.size();
+        let bytes_per_row = size.x * gpu_image.texture_format.pixel_size() as u32;
+        if write.rect.max.cmpgt(gpu_image.size).any()
+            || write.data.len() != (bytes_per_row * size.y) as usize
+        {
+            warn!(
+                "Skipped writing {} bytes to region {:?} of image {:?} with size {} and format {:?}",
+                write.data.len(),
+                write.rect,
+                write.image,
+                gpu_image.size,
+                gpu_image.texture_format
+            );
+            continue;
+        }
+
+        render_queue.write_texture(
+            ImageCopyTexture {
+                texture: &gpu_image.texture,
+                mip_level: 0,
+                origin: Origin3d {
+                    x: write.rect.min.x,
+                    y: write.rect.min.y,
+                    z: 0,
+                },
+                aspect: TextureAspect::All,
+            },
+            &write.data,
+            ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(bytes_per_row),
+                rows_per_image: None,
+            },
+            Extent3d {
+                width: size.x,
+                height: size.y,
+                depth_or_array_layers: 1,
+            },
+        );
+    }
+}
@@ -0,0 +1,65 @@
+use bevy_ecs::prelude::*;
+use bevy_math::BVec2;
+use bevy_render::Extract;
+
+use crate::{
+    CustomBlendState, ExtractedSprite, ExtractedSprites, ScissorRect, SortBias, SpriteAlphaMode,
+    SpriteSampling,
+};
+
+/// Moves the sprites extracted by Bevy's `SpritePlugin` into the [`ExtractedSprites`] of this
+/// crate, so they are drawn by the sprite pipeline of this crate instead, see
+/// [`SpriteExPlugin::render_bevy_sprites`](crate::SpriteExPlugin::render_bevy_sprites).
+///
+/// Texture atlases and slices are already resolved by Bevy's extraction. The [`SortBias`],
+/// [`CustomBlendState`] and [`ScissorRect`] of these sprites are extracted too.
+pub fn extract_bevy_sprites(
+    mut bevy_sprites: ResMut<bevy_sprite::ExtractedSprites>,
+    mut extracted_sprites: ResMut<ExtractedSprites>,
+    sprite_query: Extract<
+        Query<
+            (
+                Option<&SortBias>,
+                Option<&CustomBlendState>,
+                Option<&ScissorRect>,
+            ),
+            With<bevy_sprite::Sprite>,
+        >,
+    >,
+) {
+    for (entity, sprite) in bevy_sprites.sprites.drain() {
+        let (sort_bias, blend_state, scissor) = sprite_query
+            .get(sprite.original_entity.unwrap_or(entity))
+            .unwrap_or_default();
+        extracted_sprites.sprites.insert(
+            entity,
+            ExtractedSprite {
+                transform: sprite.transform,
+                color: sprite.color,
+                rect: sprite.rect,
+                custom_size: sprite.custom_size,
+                image_handle_id: sprite.image_handle_id,
+                flip_x: sprite.flip_x,
+                flip_y: sprite.flip_y,
+                anchor: sprite.anchor,
+                repeat: BVec2::FALSE,
+                sampling: SpriteSampling::Texture,
+                scissor: scissor.map(|scissor| scissor.0),
+                border: None,
+                posterize_levels: 0,
+                hsv_shift: None,
+                desaturate: 0.0,
+                dissolve: None,
+                color_key: None,
+                alpha_mode: SpriteAlphaMode::default(),
+                blend_state: blend_state.map(|blend_state| blend_state.0),
+                fragment_shader: None,
+                sampler: None,
+                material: None,
+                batch_break: false,
+                sort_bias: sort_bias.map_or(0.0, |sort_bias| sort_bias.0),
+                original_entity: sprite.original_entity,
+            },
+        );
+    }
+}
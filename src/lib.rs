@@ -1,33 +1,200 @@
+#![allow(clippy::type_complexity)]
+
 use bevy_app::prelude::*;
-use bevy_asset::{load_internal_asset, Assets, Handle};
+use bevy_asset::{
+    load_internal_asset, processor::LoadTransformAndSave, AssetApp, AssetEvent, AssetServer,
+    Assets, Handle,
+};
 use bevy_core_pipeline::core_2d::Transparent2d;
 use bevy_ecs::prelude::*;
 use bevy_render::{
+    camera::CameraUpdateSystem,
+    extract_component::ExtractComponentPlugin,
+    extract_resource::ExtractResourcePlugin,
     mesh::Mesh,
     primitives::Aabb,
+    render_asset::prepare_assets,
     render_phase::AddRenderCommand,
-    render_resource::{Shader, SpecializedRenderPipelines},
-    texture::Image,
+    render_resource::{Shader, SpecializedRenderPipelines, TextureFormat},
+    texture::{GpuImage, Image},
     view::{check_visibility, NoFrustumCulling, VisibilitySystems},
     ExtractSchedule, Render, RenderApp, RenderSet,
 };
-use bevy_sprite::{queue_material2d_meshes, ColorMaterial, Mesh2dHandle};
+use bevy_sprite::{queue_material2d_meshes, ColorMaterial, ImageScaleMode, Mesh2dHandle};
+use bevy_transform::TransformSystem;
+use bevy_utils::HashSet;
 
+pub use animation::*;
+pub use animation_state::*;
 pub use bundle::*;
+#[cfg(feature = "debug")]
+pub use debug::*;
+#[cfg(feature = "diagnostics")]
+pub use diagnostics::*;
+pub use fade::*;
+pub use frame_hash::*;
+pub use image_region::*;
+pub use interop::*;
+pub use material::*;
 use render::*;
+pub use render::{
+    extend_view_entities, extract_sprites, prepare_sprite_image_bind_groups, queue_sprites,
+    sort_extracted_sprites, write_sprite_buffers, DrawSprite, DrawSpriteBatch, ExtractedDissolve,
+    ExtractedSprite, ExtractedSprites, ImageBindGroups, SetSpriteDissolveBindGroup,
+    SetSpriteTextureBindGroup, SetSpriteViewBindGroup, SpriteBatch, SpriteBindGroupBudget,
+    SpriteExPipeline, SpriteExPipelineSettings, SpriteInstance, SpriteInstanceFlags,
+    SpriteInstanceParams, SpriteMeta, SpritePipelineKey, SpriteSamplerId, SpriteSamplers,
+    CORE_2D_DEPTH_FORMAT,
+};
+pub use sdf::*;
 pub use sprite::*;
+pub use sprite_grid::*;
+pub use sprite_group::*;
+pub use texture_array::*;
+pub use texture_slice::*;
 
+mod animation;
+mod animation_state;
 mod bundle;
+#[cfg(feature = "debug")]
+mod debug;
+#[cfg(feature = "diagnostics")]
+mod diagnostics;
+mod fade;
+mod frame_hash;
+mod image_region;
+mod interop;
+mod material;
 mod render;
+mod sdf;
 mod sprite;
+mod sprite_grid;
+mod sprite_group;
+mod texture_array;
+mod texture_slice;
 
 /// Adds support for 2D sprite rendering.
+///
+/// The plugin is configured through its fields, so it is added as `SpriteExPlugin::default()`,
+/// with struct update syntax to change some of the settings. It used to be a unit struct, added
+/// as `SpriteExPlugin`, which no longer compiles.
+///
+/// ```no_run
+/// # use bevy_app::App;
+/// # use bevy_sprite_ex::SpriteExPlugin;
+/// App::new().add_plugins(SpriteExPlugin {
+///     cull_back_faces: true,
+///     ..Default::default()
+/// });
+/// ```
 #[derive(Default)]
-pub struct SpriteExPlugin;
+pub struct SpriteExPlugin {
+    /// Cull sprites whose front face points away from the camera. Sprites mirrored through a
+    /// negative scale in their transform (or a negative custom size) have their winding corrected
+    /// automatically, so they stay visible.
+    pub cull_back_faces: bool,
+    /// Specialize the sprite pipelines with a depth-stencil state of this format, e.g.
+    /// [`CORE_2D_DEPTH_FORMAT`].
+    ///
+    /// Bevy's 2D pass has no depth attachment, so this is only needed when another plugin adds one
+    /// to the [`Transparent2d`] pass. Sprites then depth test against it, and only write depth with
+    /// [`SpriteAlphaMode::Mask`] or [`depth_write`](Self::depth_write).
+    pub depth_format: Option<TextureFormat>,
+    /// Make every sprite write depth when the pipelines have a [`depth_format`](Self::depth_format),
+    /// so later items such as `Mesh2d`s further away are occluded by sprites regardless of how
+    /// they are sorted.
+    ///
+    /// Semi-transparent edges then also hide what is drawn behind them later, so this suits
+    /// opaque sprite art or sprites drawn back to front.
+    pub depth_write: bool,
+    /// Offset added to the z of sprites when sorting them with the other items of the
+    /// [`Transparent2d`] phase, such as `Mesh2d`s and Bevy's own sprites.
+    ///
+    /// Items at equal z are drawn in an unspecified order. A small positive value (e.g. `0.001`,
+    /// large enough to survive the precision of the z values in use) draws sprites on top of other
+    /// items at the same z, a negative value below them. Sprites keep their position.
+    pub sort_bias: f32,
+    /// How sprites are sorted by default, e.g. [`SpriteSortMode::Y`] for top-down games instead of
+    /// setting their z from their y every frame. Cameras can override it with a [`SpriteSortMode`].
+    pub sort_mode: SpriteSortMode,
+    /// Draw sprites whose larger side covers fewer than this many pixels on screen with a
+    /// simplified pipeline, which skips RotSprite sampling, [`Border`], [`Posterize`], [`HsvShift`]
+    /// and tonemapping.
+    ///
+    /// This cuts fragment cost in dense, far zoomed-out scenes, at the price of these sprites
+    /// looking slightly different when the view tonemaps. Cameras can override it with a
+    /// [`SpriteLodThreshold`].
+    pub lod_threshold: Option<f32>,
+    /// Track sprites in a [`SpriteGrid`] with cells of this size in world units, so extraction
+    /// skips the sprites far away from every camera.
+    ///
+    /// Worth it in large worlds where most sprites are off screen. The cell size should be around
+    /// the size of the visible area, as sprites in every cell overlapping a camera are extracted.
+    pub spatial_grid_cell_size: Option<f32>,
+    /// Remove the tonemapping LUT bindings and shader defs from the sprite pipelines.
+    ///
+    /// For games whose 2D content is never tonemapped, this trims the view bind group. Sprites are
+    /// then never tonemapped nor debanded in the shader, even in views with [`Tonemapping`] set;
+    /// HDR views still tonemap in their post processing pass.
+    ///
+    /// [`Tonemapping`]: bevy_core_pipeline::tonemapping::Tonemapping
+    pub disable_tonemapping: bool,
+    /// Load `sprite.wgsl` and the modules it imports from this folder of the asset directory
+    /// instead of using the copies built into the crate, e.g. `Some("shaders/sprite_ex".into())`.
+    ///
+    /// Together with the `file_watcher` feature of Bevy, edits to the shaders are picked up while
+    /// the app is running. The files can be copied from this crate's `src/render` folder as a
+    /// starting point.
+    pub shader_asset_path: Option<String>,
+    /// Hash the prepared sprite instances and batches of every frame into a [`SpriteFrameHash`]
+    /// resource, for tests checking that the render input of a scene doesn't change.
+    pub frame_hash: bool,
+    /// Pack small images of sprites into a [`SpriteTextureArray`] with these settings at runtime,
+    /// so sprites with different images can be drawn in one batch.
+    ///
+    /// Saves bind group switches and draw calls in scenes mixing many small images that aren't
+    /// packed into an atlas, at the cost of keeping a copy of each image in the array.
+    pub texture_array: Option<SpriteTextureArraySettings>,
+    /// Draw the sprites of Bevy's `SpritePlugin` with the sprite pipeline of this crate, see
+    /// [`extract_bevy_sprites`].
+    ///
+    /// Plain `Sprite` entities, e.g. spawned by third-party crates, are then sorted and batched
+    /// together with [`SpriteEx`] entities instead of being drawn by a separate pipeline. Their
+    /// [`SortBias`], [`CustomBlendState`] and [`ScissorRect`] apply too. Needs the
+    /// `SpritePlugin`.
+    pub render_bevy_sprites: bool,
+}
+
+/// Keeps the sprite shaders loaded from the asset folder alive.
+#[derive(Resource)]
+struct SpriteShaders {
+    _imports: Vec<Handle<Shader>>,
+}
 
 pub const SPRITE_SHADER_HANDLE: Handle<Shader> = Handle::weak_from_u128(8793537950464524391);
 pub const SPRITE_VIEW_BINDINGS_SHADER_HANDLE: Handle<Shader> =
     Handle::weak_from_u128(4597317399397146678);
+pub const SPRITE_TYPES_SHADER_HANDLE: Handle<Shader> = Handle::weak_from_u128(1706585026961218420);
+pub const SPRITE_VERTEX_SHADER_HANDLE: Handle<Shader> = Handle::weak_from_u128(6139627929159118645);
+pub const SPRITE_SAMPLING_SHADER_HANDLE: Handle<Shader> =
+    Handle::weak_from_u128(3143402307897105523);
+pub const SPRITE_TONEMAPPING_SHADER_HANDLE: Handle<Shader> =
+    Handle::weak_from_u128(9218093478149255667);
+pub const SPRITE_BORDER_SHADER_HANDLE: Handle<Shader> = Handle::weak_from_u128(2567622908151530877);
+pub const SPRITE_EFFECTS_SHADER_HANDLE: Handle<Shader> =
+    Handle::weak_from_u128(7462916752811420909);
+
+/// The shader modules imported by `sprite.wgsl`, which custom pipelines can import as
+/// `bevy_sprite_ex::<file name>`.
+const SPRITE_SHADER_IMPORTS: [&str; 7] = [
+    "sprite_view_bindings.wgsl",
+    "sprite_types.wgsl",
+    "sprite_vertex.wgsl",
+    "sprite_sampling.wgsl",
+    "sprite_tonemapping.wgsl",
+    "sprite_border.wgsl",
+    "sprite_effects.wgsl",
+];
 
 /// System set for sprite rendering.
 #[derive(Debug, Hash, PartialEq, Eq, Clone, SystemSet)]
@@ -46,53 +213,233 @@ pub type WithSprite = With<SpriteEx>;
 
 impl Plugin for SpriteExPlugin {
     fn build(&self, app: &mut App) {
-        load_internal_asset!(
-            app,
-            SPRITE_SHADER_HANDLE,
-            "render/sprite.wgsl",
-            Shader::from_wgsl
-        );
-        load_internal_asset!(
-            app,
-            SPRITE_VIEW_BINDINGS_SHADER_HANDLE,
-            "render/sprite_view_bindings.wgsl",
-            Shader::from_wgsl
-        );
-
-        app.register_type::<SpriteEx>().add_systems(
-            PostUpdate,
-            (
-                calculate_bounds_2d.in_set(VisibilitySystems::CalculateBounds),
+        let sprite_shader = if let Some(path) = &self.shader_asset_path {
+            let asset_server = app.world().resource::<AssetServer>();
+            let sprite_shader = asset_server.load(format!("{path}/sprite.wgsl"));
+            let imports = SPRITE_SHADER_IMPORTS
+                .iter()
+                .map(|file| asset_server.load(format!("{path}/{file}")))
+                .collect();
+            app.insert_resource(SpriteShaders { _imports: imports });
+            sprite_shader
+        } else {
+            load_internal_asset!(
+                app,
+                SPRITE_SHADER_HANDLE,
+                "render/sprite.wgsl",
+                Shader::from_wgsl
+            );
+            load_internal_asset!(
+                app,
+                SPRITE_VIEW_BINDINGS_SHADER_HANDLE,
+                "render/sprite_view_bindings.wgsl",
+                Shader::from_wgsl
+            );
+            load_internal_asset!(
+                app,
+                SPRITE_TYPES_SHADER_HANDLE,
+                "render/sprite_types.wgsl",
+                Shader::from_wgsl
+            );
+            load_internal_asset!(
+                app,
+                SPRITE_VERTEX_SHADER_HANDLE,
+                "render/sprite_vertex.wgsl",
+                Shader::from_wgsl
+            );
+            load_internal_asset!(
+                app,
+                SPRITE_SAMPLING_SHADER_HANDLE,
+                "render/sprite_sampling.wgsl",
+                Shader::from_wgsl
+            );
+            load_internal_asset!(
+                app,
+                SPRITE_TONEMAPPING_SHADER_HANDLE,
+                "render/sprite_tonemapping.wgsl",
+                Shader::from_wgsl
+            );
+            load_internal_asset!(
+                app,
+                SPRITE_BORDER_SHADER_HANDLE,
+                "render/sprite_border.wgsl",
+                Shader::from_wgsl
+            );
+            load_internal_asset!(
+                app,
+                SPRITE_EFFECTS_SHADER_HANDLE,
+                "render/sprite_effects.wgsl",
+                Shader::from_wgsl
+            );
+            SPRITE_SHADER_HANDLE
+        };
+
+        app.register_asset_processor::<SdfImageProcessor>(LoadTransformAndSave::new(
+            SdfTransformer,
+            SdfImageSaver,
+        ));
+
+        app.init_asset::<SpriteAnimationClip>()
+            .init_asset_loader::<SpriteAnimationClipLoader>()
+            .add_event::<AnimationFrameEvent>()
+            .add_event::<AnimationFinishedEvent>();
+
+        app.register_type::<SpriteEx>()
+            .register_type::<RectSpace>()
+            .register_type::<FlipOrigin>()
+            .register_type::<SpriteSampling>()
+            .register_type::<SpriteAlphaMode>()
+            .register_type::<ColorKey>()
+            .register_type::<ScissorRect>()
+            .register_type::<Border>()
+            .register_type::<ExtractOffset>()
+            .register_type::<Posterize>()
+            .register_type::<HsvShift>()
+            .register_type::<Dissolve>()
+            .register_type::<BatchBreak>()
+            .register_type::<TintStack>()
+            .register_type::<SpriteAnimation>()
+            .register_type::<AnimationMode>()
+            .register_type::<SpriteAnimationStateMachine>()
+            .register_type::<FadeIn>()
+            .register_type::<FadeOut>()
+            .register_type::<FadeOutEnd>()
+            .register_type::<SpriteBindGroupBudget>()
+            .register_type::<SpriteExposure>()
+            .register_type::<SpriteLodThreshold>()
+            .register_type::<SpriteSortMode>()
+            .register_type::<SortBias>()
+            .register_type::<GroupOpacity>()
+            .register_type::<InheritedTint>()
+            .register_type::<ImageScaleMode>()
+            .register_type::<SpriteGroup>()
+            .register_type::<SpriteGroupCamera>()
+            .init_resource::<SpriteBindGroupBudget>()
+            .add_plugins((
+                ExtractResourcePlugin::<SpriteBindGroupBudget>::default(),
+                ExtractComponentPlugin::<SpriteExposure>::default(),
+                ExtractComponentPlugin::<SpriteLodThreshold>::default(),
+                ExtractComponentPlugin::<SpriteSortMode>::default(),
+            ))
+            .init_resource::<ImageRegionWrites>()
+            .add_systems(
+                Update,
+                (update_animation_state_machines, animate_sprites).chain(),
+            )
+            .add_systems(
+                PostUpdate,
                 (
-                    check_visibility::<WithMesh2d>,
-                    check_visibility::<WithSprite>,
-                )
-                    .in_set(VisibilitySystems::CheckVisibility),
-            ),
-        );
+                    update_fades.before(VisibilitySystems::VisibilityPropagate),
+                    (update_sprite_groups, remove_sprite_groups)
+                        .before(VisibilitySystems::CheckVisibility),
+                    (
+                        compute_slices_on_asset_event,
+                        compute_slices_on_sprite_change,
+                    )
+                        .in_set(SpriteSystem::ComputeSlices),
+                    calculate_bounds_2d.in_set(VisibilitySystems::CalculateBounds),
+                    (
+                        check_visibility::<WithMesh2d>,
+                        check_visibility::<WithSprite>,
+                    )
+                        .in_set(VisibilitySystems::CheckVisibility),
+                ),
+            );
+
+        if let Some(cell_size) = self.spatial_grid_cell_size {
+            app.insert_resource(SpriteGrid::new(cell_size)).add_systems(
+                PostUpdate,
+                (update_sprite_grid, collect_sprite_grid_candidates)
+                    .chain()
+                    .after(TransformSystem::TransformPropagate)
+                    .after(CameraUpdateSystem)
+                    .after(VisibilitySystems::CalculateBounds),
+            );
+        }
+
+        let frame_hash = self.frame_hash.then(SpriteFrameHash::default);
+        if let Some(frame_hash) = &frame_hash {
+            app.insert_resource(frame_hash.clone());
+        }
 
         if let Some(render_app) = app.get_sub_app_mut(RenderApp) {
+            if let Some(frame_hash) = frame_hash {
+                render_app
+                    .insert_resource(frame_hash)
+                    .add_systems(Render, hash_sprite_frame.in_set(RenderSet::Render));
+            }
+
+            if self.texture_array.is_some() {
+                render_app
+                    .add_systems(
+                        ExtractSchedule,
+                        extract_sprite_texture_array_images.after(SpriteSystem::ExtractSprites),
+                    )
+                    .add_systems(
+                        Render,
+                        prepare_sprite_texture_array
+                            .in_set(RenderSet::PrepareAssets)
+                            .after(prepare_assets::<GpuImage>),
+                    );
+            }
+
+            if self.render_bevy_sprites {
+                render_app.add_systems(
+                    ExtractSchedule,
+                    extract_bevy_sprites
+                        .in_set(SpriteSystem::ExtractSprites)
+                        .after(extract_sprites)
+                        .after(bevy_sprite::SpriteSystem::ExtractSprites),
+                );
+            }
+
             render_app
+                .insert_resource(SpriteExPipelineSettings {
+                    cull_back_faces: self.cull_back_faces,
+                    depth_format: self.depth_format,
+                    depth_write: self.depth_write,
+                    sort_bias: self.sort_bias,
+                    sort_mode: self.sort_mode,
+                    lod_threshold: self.lod_threshold,
+                    disable_tonemapping: self.disable_tonemapping,
+                    texture_array: self.texture_array,
+                    bevy_sprites: self.render_bevy_sprites,
+                    shader: sprite_shader,
+                })
                 .init_resource::<ImageBindGroups>()
+                .init_resource::<SpriteSamplers>()
                 .init_resource::<SpecializedRenderPipelines<SpriteExPipeline>>()
+                .init_resource::<SpecializedRenderPipelines<SpriteFragmentShaderPipeline>>()
                 .init_resource::<SpriteMeta>()
                 .init_resource::<ExtractedSprites>()
                 .init_resource::<SpriteAssetEvents>()
+                .init_resource::<ImageRegionWrites>()
+                .init_resource::<SpriteExposureUniforms>()
                 .add_render_command::<Transparent2d, DrawSprite>()
                 .add_systems(
                     ExtractSchedule,
                     (
                         extract_sprites.in_set(SpriteSystem::ExtractSprites),
                         extract_sprite_events,
+                        extract_image_region_writes,
                     ),
                 )
                 .add_systems(
                     Render,
                     (
+                        write_image_regions
+                            .in_set(RenderSet::PrepareAssets)
+                            .after(prepare_assets::<GpuImage>),
+                        // Before any system queues sprites
+                        sort_extracted_sprites.in_set(RenderSet::PrepareAssets),
                         queue_sprites
                             .in_set(RenderSet::Queue)
                             .ambiguous_with(queue_material2d_meshes::<ColorMaterial>),
+                        prepare_sprite_exposure_uniforms.in_set(RenderSet::PrepareResources),
                         prepare_sprite_image_bind_groups.in_set(RenderSet::PrepareBindGroups),
+                        write_sprite_buffers
+                            .in_set(RenderSet::PrepareBindGroups)
+                            .after(prepare_sprite_image_bind_groups),
                         prepare_sprite_view_bind_groups.in_set(RenderSet::PrepareBindGroups),
                     ),
                 );
@@ -101,7 +448,12 @@ impl Plugin for SpriteExPlugin {
 
     fn finish(&self, app: &mut App) {
         if let Some(render_app) = app.get_sub_app_mut(RenderApp) {
-            render_app.init_resource::<SpriteExPipeline>();
+            render_app
+                .init_resource::<SpriteExPipeline>()
+                .init_resource::<SpriteFragmentShaderPipeline>();
+            if self.texture_array.is_some() {
+                render_app.init_resource::<SpriteTextureArray>();
+            }
         }
     }
 }
@@ -109,21 +461,27 @@ impl Plugin for SpriteExPlugin {
 /// System calculating and inserting an [`Aabb`] component to entities with either:
 /// - a `Mesh2dHandle` component,
 /// - a `Sprite` and `Handle<Image>` components,
+///
 /// and without a [`NoFrustumCulling`] component.
 ///
+/// Sprites without a custom size or rect are sized like their image, so their bounds are
+/// recalculated when it is loaded or modified. Until then they have no [`Aabb`] and aren't culled.
+///
 /// Used in system set [`VisibilitySystems::CalculateBounds`].
 pub fn calculate_bounds_2d(
     mut commands: Commands,
     meshes: Res<Assets<Mesh>>,
-    _images: Res<Assets<Image>>,
+    images: Res<Assets<Image>>,
+    mut image_events: EventReader<AssetEvent<Image>>,
     meshes_without_aabb: Query<(Entity, &Mesh2dHandle), (Without<Aabb>, Without<NoFrustumCulling>)>,
     sprites_to_recalculate_aabb: Query<
-        (Entity, &SpriteEx, &Handle<Image>),
+        (Entity, &SpriteEx, &Handle<Image>, Has<Aabb>),
         (
-            Or<(Without<Aabb>, Changed<SpriteEx>)>,
+            Or<(Without<Aabb>, Changed<SpriteEx>, Changed<Handle<Image>>)>,
             Without<NoFrustumCulling>,
         ),
     >,
+    sprites: Query<(Entity, &SpriteEx, &Handle<Image>, Has<Aabb>), Without<NoFrustumCulling>>,
 ) {
     for (entity, mesh_handle) in &meshes_without_aabb {
         if let Some(mesh) = meshes.get(&mesh_handle.0) {
@@ -132,16 +490,42 @@ pub fn calculate_bounds_2d(
             }
         }
     }
-    for (entity, sprite, _texture_handle) in &sprites_to_recalculate_aabb {
-        if let Some(size) = sprite
-            .custom_size
-            .or_else(|| sprite.rect.map(|rect| rect.size()))
-        {
-            let aabb = Aabb {
-                center: (-sprite.anchor.as_vec() * size).extend(0.0).into(),
-                half_extents: (0.5 * size).extend(0.0).into(),
-            };
-            commands.entity(entity).try_insert(aabb);
-        }
+
+    let mut update_aabb =
+        |(entity, sprite, texture_handle, has_aabb): (Entity, &SpriteEx, &Handle<Image>, bool)| {
+            match sprite.quad_size(images.get(texture_handle)) {
+                Some(size) => {
+                    let aabb = Aabb {
+                        center: (-sprite.flipped_anchor() * size).extend(0.0).into(),
+                        half_extents: (0.5 * size).extend(0.0).into(),
+                    };
+                    commands.entity(entity).try_insert(aabb);
+                }
+                // A stale Aabb would cull the sprite with its previous size
+                None if has_aabb => {
+                    commands.entity(entity).remove::<Aabb>();
+                }
+                None => {}
+            }
+        };
+    sprites_to_recalculate_aabb
+        .iter()
+        .for_each(&mut update_aabb);
+
+    let changed_images: HashSet<_> = image_events
+        .read()
+        .filter_map(|event| match event {
+            AssetEvent::LoadedWithDependencies { id } | AssetEvent::Modified { id } => Some(*id),
+            _ => None,
+        })
+        .collect();
+    if !changed_images.is_empty() {
+        sprites
+            .iter()
+            .filter(|(entity, _, texture_handle, _)| {
+                changed_images.contains(&texture_handle.id())
+                    && !sprites_to_recalculate_aabb.contains(*entity)
+            })
+            .for_each(update_aabb);
     }
 }
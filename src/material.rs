@@ -0,0 +1,308 @@
+use std::marker::PhantomData;
+
+use bevy_app::{App, Plugin};
+use bevy_asset::{Asset, AssetApp, AssetId, AssetServer, Handle};
+use bevy_core_pipeline::{
+    core_2d::Transparent2d,
+    tonemapping::{DebandDither, Tonemapping},
+};
+use bevy_ecs::{
+    prelude::*,
+    system::{lifetimeless::SRes, SystemParamItem},
+};
+use bevy_render::{
+    render_asset::{PrepareAssetError, RenderAsset, RenderAssetPlugin, RenderAssets},
+    render_phase::{
+        AddRenderCommand, DrawFunctions, PhaseItem, PhaseItemExtraIndex, RenderCommand,
+        RenderCommandResult, SetItemPipeline, TrackedRenderPass, ViewSortedRenderPhases,
+    },
+    render_resource::{
+        AsBindGroup, AsBindGroupError, BindGroup, BindGroupLayout, PipelineCache,
+        RenderPipelineDescriptor, Shader, ShaderRef, SpecializedRenderPipeline,
+        SpecializedRenderPipelines,
+    },
+    renderer::RenderDevice,
+    texture::{FallbackImage, GpuImage},
+    view::{ExtractedView, Msaa, ViewTarget, VisibleEntities},
+    Extract, ExtractSchedule, Render, RenderApp, RenderSet,
+};
+use fixedbitset::FixedBitSet;
+
+use crate::{
+    extend_view_entities, sprite_view_key, DrawSpriteBatch, ExtractedSprites,
+    SetSpriteTextureBindGroup, SetSpriteViewBindGroup, SpriteEx, SpriteExPipeline,
+    SpriteExPipelineSettings, SpritePipelineKey, SpriteSortMode, SpriteSystem, WithSprite,
+};
+
+/// Materials drawing [`SpriteEx`] entities with extra bindings and their own fragment shader,
+/// added to an entity as a `Handle<M>` next to its [`SpriteEx`]. See [`SpriteExMaterialPlugin`].
+///
+/// The material's bind group is bound at index 2, after the view bind group and the sprite's image
+/// bind group of `sprite.wgsl`. A fragment shader can import the sprite modules, e.g.
+/// `bevy_sprite_ex::sprite_types::VertexOutput` for its input and
+/// `bevy_sprite_ex::sprite_sampling::sample_sprite` to sample the sprite's image. The bind group
+/// data of the material is not part of the pipeline key.
+///
+/// The material's bind group takes the place of the noise texture of a [`Dissolve`], so a
+/// [`Dissolve`] on a sprite with a material is ignored.
+///
+/// [`Dissolve`]: crate::Dissolve
+pub trait SpriteExMaterial: AsBindGroup + Asset + Clone + Sized {
+    /// Returns this material's fragment shader. If [`ShaderRef::Default`] is returned, the fragment
+    /// shader of the sprite pipeline is used.
+    fn fragment_shader() -> ShaderRef {
+        ShaderRef::Default
+    }
+
+    /// Customizes the descriptor of the sprite pipeline specialized for `key`.
+    #[allow(unused_variables)]
+    #[inline]
+    fn specialize(descriptor: &mut RenderPipelineDescriptor, key: SpritePipelineKey) {}
+}
+
+/// Adds rendering of [`SpriteEx`] entities with a `Handle<M>` using the [`SpriteExMaterial`] `M`.
+///
+/// Add it after [`SpriteExPlugin`](crate::SpriteExPlugin). Sprites with a material always use the
+/// full pipeline, never the LOD fast path, and are batched with successive sprites using the
+/// same material and image.
+pub struct SpriteExMaterialPlugin<M: SpriteExMaterial>(PhantomData<M>);
+
+impl<M: SpriteExMaterial> Default for SpriteExMaterialPlugin<M> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<M: SpriteExMaterial> Plugin for SpriteExMaterialPlugin<M> {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<M>()
+            .add_plugins(RenderAssetPlugin::<PreparedSpriteExMaterial<M>>::default());
+
+        if let Some(render_app) = app.get_sub_app_mut(RenderApp) {
+            render_app
+                .add_render_command::<Transparent2d, DrawSpriteMaterial<M>>()
+                .init_resource::<SpecializedRenderPipelines<SpriteExMaterialPipeline<M>>>()
+                .add_systems(
+                    ExtractSchedule,
+                    extract_sprite_materials::<M>.after(SpriteSystem::ExtractSprites),
+                )
+                .add_systems(
+                    Render,
+                    queue_sprite_materials::<M>
+                        .in_set(RenderSet::Queue)
+                        .ambiguous_with(crate::queue_sprites),
+                );
+        }
+    }
+
+    fn finish(&self, app: &mut App) {
+        if let Some(render_app) = app.get_sub_app_mut(RenderApp) {
+            render_app.init_resource::<SpriteExMaterialPipeline<M>>();
+        }
+    }
+}
+
+/// Render pipeline data for a given [`SpriteExMaterial`].
+#[derive(Resource)]
+pub struct SpriteExMaterialPipeline<M: SpriteExMaterial> {
+    pub sprite_pipeline: SpriteExPipeline,
+    pub material_layout: BindGroupLayout,
+    pub fragment_shader: Option<Handle<Shader>>,
+    marker: PhantomData<M>,
+}
+
+impl<M: SpriteExMaterial> FromWorld for SpriteExMaterialPipeline<M> {
+    fn from_world(world: &mut World) -> Self {
+        let asset_server = world.resource::<AssetServer>();
+        let render_device = world.resource::<RenderDevice>();
+        let fragment_shader = match M::fragment_shader() {
+            ShaderRef::Default => None,
+            ShaderRef::Handle(handle) => Some(handle),
+            ShaderRef::Path(path) => Some(asset_server.load(path)),
+        };
+        Self {
+            sprite_pipeline: world.resource::<SpriteExPipeline>().clone(),
+            material_layout: M::bind_group_layout(render_device),
+            fragment_shader,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<M: SpriteExMaterial> SpecializedRenderPipeline for SpriteExMaterialPipeline<M> {
+    type Key = SpritePipelineKey;
+
+    fn specialize(&self, key: Self::Key) -> RenderPipelineDescriptor {
+        let mut descriptor = self.sprite_pipeline.specialize(key);
+        if let (Some(shader), Some(fragment)) = (&self.fragment_shader, &mut descriptor.fragment) {
+            fragment.shader = shader.clone();
+        }
+        descriptor.layout.push(self.material_layout.clone());
+        M::specialize(&mut descriptor, key);
+        descriptor
+    }
+}
+
+/// The bind group of a [`SpriteExMaterial`] asset.
+pub struct PreparedSpriteExMaterial<M: SpriteExMaterial> {
+    pub bind_group: BindGroup,
+    marker: PhantomData<M>,
+}
+
+impl<M: SpriteExMaterial> RenderAsset for PreparedSpriteExMaterial<M> {
+    type SourceAsset = M;
+
+    type Param = (
+        SRes<RenderDevice>,
+        SRes<RenderAssets<GpuImage>>,
+        SRes<FallbackImage>,
+        SRes<SpriteExMaterialPipeline<M>>,
+    );
+
+    fn prepare_asset(
+        material: Self::SourceAsset,
+        (render_device, images, fallback_image, pipeline): &mut SystemParamItem<Self::Param>,
+    ) -> Result<Self, PrepareAssetError<Self::SourceAsset>> {
+        match material.as_bind_group(
+            &pipeline.material_layout,
+            render_device,
+            images,
+            fallback_image,
+        ) {
+            Ok(prepared) => Ok(PreparedSpriteExMaterial {
+                bind_group: prepared.bind_group,
+                marker: PhantomData,
+            }),
+            Err(AsBindGroupError::RetryNextUpdate) => {
+                Err(PrepareAssetError::RetryNextUpdate(material))
+            }
+        }
+    }
+}
+
+/// Sets the material of the extracted sprites of entities with a `Handle<M>`, and of the sprites
+/// derived from them, and drops their [`Dissolve`](crate::Dissolve).
+pub fn extract_sprite_materials<M: SpriteExMaterial>(
+    mut extracted_sprites: ResMut<ExtractedSprites>,
+    materials: Extract<Query<&Handle<M>, With<SpriteEx>>>,
+) {
+    if materials.is_empty() {
+        return;
+    }
+    for (entity, sprite) in &mut extracted_sprites.sprites {
+        if let Ok(material) = materials.get(sprite.original_entity.unwrap_or(*entity)) {
+            sprite.material = Some(material.id().untyped());
+            sprite.dissolve = None;
+        }
+    }
+}
+
+/// Queues the extracted sprites with a material of type `M`.
+#[allow(clippy::too_many_arguments)]
+pub fn queue_sprite_materials<M: SpriteExMaterial>(
+    mut view_entities: Local<FixedBitSet>,
+    draw_functions: Res<DrawFunctions<Transparent2d>>,
+    material_pipeline: Res<SpriteExMaterialPipeline<M>>,
+    settings: Res<SpriteExPipelineSettings>,
+    mut pipelines: ResMut<SpecializedRenderPipelines<SpriteExMaterialPipeline<M>>>,
+    pipeline_cache: Res<PipelineCache>,
+    msaa: Res<Msaa>,
+    extracted_sprites: Res<ExtractedSprites>,
+    materials: Res<RenderAssets<PreparedSpriteExMaterial<M>>>,
+    mut transparent_render_phases: ResMut<ViewSortedRenderPhases<Transparent2d>>,
+    views: Query<(
+        Entity,
+        &VisibleEntities,
+        &ExtractedView,
+        Option<&ViewTarget>,
+        Option<&Tonemapping>,
+        Option<&DebandDither>,
+        Option<&SpriteSortMode>,
+    )>,
+) {
+    let draw_function = draw_functions.read().id::<DrawSpriteMaterial<M>>();
+
+    for (view_entity, visible_entities, view, view_target, tonemapping, dither, sort_mode) in &views
+    {
+        let Some(transparent_phase) = transparent_render_phases.get_mut(&view_entity) else {
+            continue;
+        };
+
+        let view_key = sprite_view_key(view, view_target, tonemapping, dither, &msaa, &settings);
+        let sort_mode = sort_mode.copied().unwrap_or(settings.sort_mode);
+
+        view_entities.clear();
+        extend_view_entities::<WithSprite>(&mut view_entities, visible_entities);
+
+        for (entity, extracted_sprite) in extracted_sprites.iter_visible(&view_entities) {
+            let Some(material) = extracted_sprite
+                .material
+                .and_then(|material| material.try_typed::<M>().ok())
+            else {
+                continue;
+            };
+            if materials.get(material).is_none() {
+                continue;
+            }
+
+            let key = extracted_sprite.pipeline_key(view_key, &settings);
+            transparent_phase.add(Transparent2d {
+                draw_function,
+                pipeline: pipelines.specialize(&pipeline_cache, &material_pipeline, key),
+                entity: *entity,
+                sort_key: extracted_sprites.sort_key(
+                    extracted_sprite,
+                    settings.sort_bias,
+                    sort_mode,
+                ),
+                // batch_range and dynamic_offset will be calculated in prepare_sprites
+                batch_range: 0..0,
+                extra_index: PhaseItemExtraIndex::NONE,
+            });
+        }
+    }
+}
+
+/// [`RenderCommand`] for sprites with a [`SpriteExMaterial`].
+pub type DrawSpriteMaterial<M> = (
+    SetItemPipeline,
+    SetSpriteViewBindGroup<0>,
+    SetSpriteTextureBindGroup<1>,
+    SetSpriteMaterialBindGroup<M, 2>,
+    DrawSpriteBatch,
+);
+
+pub struct SetSpriteMaterialBindGroup<M: SpriteExMaterial, const I: usize>(PhantomData<M>);
+
+impl<P: PhaseItem, M: SpriteExMaterial, const I: usize> RenderCommand<P>
+    for SetSpriteMaterialBindGroup<M, I>
+{
+    type Param = (
+        SRes<RenderAssets<PreparedSpriteExMaterial<M>>>,
+        SRes<ExtractedSprites>,
+    );
+    type ViewQuery = ();
+    type ItemQuery = ();
+
+    #[inline]
+    fn render<'w>(
+        item: &P,
+        _view: (),
+        _item_query: Option<()>,
+        (materials, extracted_sprites): SystemParamItem<'w, '_, Self::Param>,
+        pass: &mut TrackedRenderPass<'w>,
+    ) -> RenderCommandResult {
+        let materials = materials.into_inner();
+        let Some(material) = extracted_sprites
+            .sprites
+            .get(&item.entity())
+            .and_then(|sprite| sprite.material)
+            .and_then(|material| material.try_typed::<M>().ok())
+            .and_then(|material: AssetId<M>| materials.get(material))
+        else {
+            return RenderCommandResult::Failure;
+        };
+        pass.set_bind_group(I, &material.bind_group, &[]);
+        RenderCommandResult::Success
+    }
+}
@@ -1,6 +1,6 @@
 use std::ops::Range;
 
-use bevy_asset::{AssetEvent, AssetId, Handle};
+use bevy_asset::{AssetEvent, AssetId, Assets, Handle, UntypedAssetId};
 use bevy_color::{ColorToComponents, LinearRgba};
 use bevy_core_pipeline::{
     core_2d::Transparent2d,
@@ -9,26 +9,33 @@ use bevy_core_pipeline::{
         TonemappingLuts,
     },
 };
-use bevy_ecs::{entity::EntityHashMap, query::ROQueryItem};
+use bevy_ecs::{entity::EntityHash, query::ROQueryItem};
 use bevy_ecs::{
     prelude::*,
     system::{lifetimeless::*, SystemParamItem, SystemState},
 };
-use bevy_math::{Affine3A, FloatOrd, Quat, Rect, Vec2, Vec4};
+use bevy_hierarchy::{HierarchyQueryExt, Parent};
+use bevy_math::{
+    Affine3A, BVec2, FloatOrd, Mat4, Quat, Rect, URect, UVec2, Vec2, Vec3, Vec3A, Vec4,
+    Vec4Swizzles,
+};
+use bevy_reflect::{std_traits::ReflectDefault, Reflect};
 use bevy_render::{
+    extract_resource::ExtractResource,
+    globals::{GlobalsBuffer, GlobalsUniform},
     render_asset::RenderAssets,
     render_phase::{
         DrawFunctions, PhaseItem, PhaseItemExtraIndex, RenderCommand, RenderCommandResult,
         SetItemPipeline, TrackedRenderPass, ViewSortedRenderPhases,
     },
     render_resource::{
-        binding_types::{sampler, texture_2d, uniform_buffer},
+        binding_types::{sampler, texture_2d, texture_2d_array, uniform_buffer},
         *,
     },
     renderer::{RenderDevice, RenderQueue},
     texture::{
         BevyDefault, DefaultImageSampler, FallbackImage, GpuImage, Image, ImageSampler,
-        TextureFormatPixelInfo,
+        ImageSamplerDescriptor, TextureFormatPixelInfo,
     },
     view::{
         ExtractedView, Msaa, ViewTarget, ViewUniform, ViewUniformOffset, ViewUniforms,
@@ -37,17 +44,103 @@ use bevy_render::{
     Extract,
 };
 use bevy_transform::components::GlobalTransform;
-use bevy_utils::HashMap;
+use bevy_utils::{Duration, HashMap, Instant};
 use bytemuck::{Pod, Zeroable};
 use fixedbitset::FixedBitSet;
+use indexmap::IndexMap;
+
+use crate::{
+    BatchBreak, Border, ColorKey, ComputedSlices, CustomBlendState, Dissolve, ExtractOffset,
+    FadeIn, FadeOut, GroupOpacity, HsvShift, InheritedTint, Posterize, RectSpace, ScissorRect,
+    SortBias, SpriteAlphaMode, SpriteEx, SpriteExposure, SpriteFragmentShader, SpriteGrid,
+    SpriteLodThreshold, SpriteSampler, SpriteSampling, SpriteSortMode, SpriteTextureArray,
+    SpriteTextureArraySettings, TintStack, WithSprite, SPRITE_SHADER_HANDLE,
+};
 
-use crate::{SpriteEx, WithSprite, SPRITE_SHADER_HANDLE};
+/// The depth format used by the 2D core pipeline of later Bevy versions, and by third party
+/// renderers adding a depth buffer to the 2D pass.
+pub const CORE_2D_DEPTH_FORMAT: TextureFormat = TextureFormat::Depth32Float;
+
+/// Render world settings of the sprite pipeline, set from [`SpriteExPlugin`](crate::SpriteExPlugin).
+#[derive(Resource, Clone)]
+pub struct SpriteExPipelineSettings {
+    /// Cull back faces, see
+    /// [`SpriteExPlugin::cull_back_faces`](crate::SpriteExPlugin::cull_back_faces).
+    pub cull_back_faces: bool,
+    /// The format of the depth-stencil state of the pipelines, `None` for none, see
+    /// [`SpriteExPlugin::depth_format`](crate::SpriteExPlugin::depth_format).
+    pub depth_format: Option<TextureFormat>,
+    /// Write depth from every sprite, see
+    /// [`SpriteExPlugin::depth_write`](crate::SpriteExPlugin::depth_write).
+    pub depth_write: bool,
+    /// Offset added to the z sort key of sprites, see
+    /// [`SpriteExPlugin::sort_bias`](crate::SpriteExPlugin::sort_bias).
+    pub sort_bias: f32,
+    /// How sprites are sorted in views without their own [`SpriteSortMode`].
+    pub sort_mode: SpriteSortMode,
+    /// The screen size below which sprites use the fast path pipeline in views without their own
+    /// [`SpriteLodThreshold`].
+    pub lod_threshold: Option<f32>,
+    /// Leave the tonemapping bindings and shader defs out of the pipelines, see
+    /// [`SpriteExPlugin::disable_tonemapping`](crate::SpriteExPlugin::disable_tonemapping).
+    pub disable_tonemapping: bool,
+    /// Settings of the [`SpriteTextureArray`](crate::SpriteTextureArray), `None` to not create one.
+    pub texture_array: Option<SpriteTextureArraySettings>,
+    /// Queue the sprites of Bevy's `SpritePlugin` too, see
+    /// [`extract_bevy_sprites`](crate::extract_bevy_sprites).
+    pub bevy_sprites: bool,
+    /// The shader with the `vertex` and `fragment` entry points of the sprite pipeline.
+    pub shader: Handle<Shader>,
+}
 
-#[derive(Resource)]
+impl Default for SpriteExPipelineSettings {
+    fn default() -> Self {
+        Self {
+            cull_back_faces: false,
+            depth_format: None,
+            depth_write: false,
+            sort_bias: 0.0,
+            sort_mode: SpriteSortMode::Z,
+            lod_threshold: None,
+            disable_tonemapping: false,
+            texture_array: None,
+            bevy_sprites: false,
+            shader: SPRITE_SHADER_HANDLE,
+        }
+    }
+}
+
+/// Limits how many sprite image bind groups are created per frame.
+///
+/// When many new images become visible at once, creating all their bind groups in a single frame
+/// causes a hitch. With a budget, images used by the most visible sprites are prepared first and
+/// the rest are drawn with a white placeholder texture (tinted by the sprite's color) until a later
+/// frame has budget left for them.
+#[derive(Resource, ExtractResource, Clone, Debug, Default, Reflect)]
+#[reflect(Resource, Default)]
+pub struct SpriteBindGroupBudget {
+    /// The maximum number of bind groups created per frame.
+    pub max_count: Option<usize>,
+    /// The maximum time spent creating bind groups per frame.
+    pub max_time: Option<Duration>,
+}
+
+impl SpriteBindGroupBudget {
+    fn is_limited(&self) -> bool {
+        self.max_count.is_some() || self.max_time.is_some()
+    }
+}
+
+#[derive(Resource, Clone)]
 pub struct SpriteExPipeline {
     view_layout: BindGroupLayout,
     material_layout: BindGroupLayout,
-    #[allow(dead_code)]
+    pub(crate) texture_array_layout: BindGroupLayout,
+    cull_mode: Option<Face>,
+    depth_format: Option<TextureFormat>,
+    depth_write: bool,
+    tonemapping: bool,
+    shader: Handle<Shader>,
     dummy_white_gpu_image: GpuImage,
 }
 
@@ -57,27 +150,47 @@ impl FromWorld for SpriteExPipeline {
             Res<RenderDevice>,
             Res<DefaultImageSampler>,
             Res<RenderQueue>,
+            Option<Res<SpriteExPipelineSettings>>,
         )> = SystemState::new(world);
-        let (render_device, default_sampler, render_queue) = system_state.get_mut(world);
-
-        let tonemapping_lut_entries = get_lut_bind_group_layout_entries();
-        let view_layout = render_device.create_bind_group_layout(
-            "sprite_view_layout",
-            &BindGroupLayoutEntries::with_indices(
-                ShaderStages::VERTEX_FRAGMENT,
-                (
-                    (0, uniform_buffer::<ViewUniform>(true)),
+        let (render_device, default_sampler, render_queue, settings) = system_state.get_mut(world);
+        let settings = settings
+            .map(|settings| settings.clone())
+            .unwrap_or_default();
+
+        let view_layout = if settings.disable_tonemapping {
+            render_device.create_bind_group_layout(
+                "sprite_view_layout",
+                &BindGroupLayoutEntries::with_indices(
+                    ShaderStages::VERTEX_FRAGMENT,
                     (
-                        1,
-                        tonemapping_lut_entries[0].visibility(ShaderStages::FRAGMENT),
+                        (0, uniform_buffer::<ViewUniform>(true)),
+                        (3, uniform_buffer::<GlobalsUniform>(false)),
+                        (4, uniform_buffer::<f32>(true)),
                     ),
+                ),
+            )
+        } else {
+            let tonemapping_lut_entries = get_lut_bind_group_layout_entries();
+            render_device.create_bind_group_layout(
+                "sprite_view_layout",
+                &BindGroupLayoutEntries::with_indices(
+                    ShaderStages::VERTEX_FRAGMENT,
                     (
-                        2,
-                        tonemapping_lut_entries[1].visibility(ShaderStages::FRAGMENT),
+                        (0, uniform_buffer::<ViewUniform>(true)),
+                        (
+                            1,
+                            tonemapping_lut_entries[0].visibility(ShaderStages::FRAGMENT),
+                        ),
+                        (
+                            2,
+                            tonemapping_lut_entries[1].visibility(ShaderStages::FRAGMENT),
+                        ),
+                        (3, uniform_buffer::<GlobalsUniform>(false)),
+                        (4, uniform_buffer::<f32>(true)),
                     ),
                 ),
-            ),
-        );
+            )
+        };
 
         let material_layout = render_device.create_bind_group_layout(
             "sprite_material_layout",
@@ -89,6 +202,16 @@ impl FromWorld for SpriteExPipeline {
                 ),
             ),
         );
+        let texture_array_layout = render_device.create_bind_group_layout(
+            "sprite_texture_array_layout",
+            &BindGroupLayoutEntries::sequential(
+                ShaderStages::FRAGMENT,
+                (
+                    texture_2d_array(TextureSampleType::Float { filterable: true }),
+                    sampler(SamplerBindingType::Filtering),
+                ),
+            ),
+        );
         let dummy_white_gpu_image = {
             let image = Image::default();
             let texture = render_device.create_texture(&image.texture_descriptor);
@@ -124,6 +247,12 @@ impl FromWorld for SpriteExPipeline {
         SpriteExPipeline {
             view_layout,
             material_layout,
+            texture_array_layout,
+            cull_mode: settings.cull_back_faces.then_some(Face::Back),
+            depth_format: settings.depth_format,
+            depth_write: settings.depth_write,
+            tonemapping: !settings.disable_tonemapping,
+            shader: settings.shader,
             dummy_white_gpu_image,
         }
     }
@@ -134,11 +263,23 @@ bitflags::bitflags! {
     #[repr(transparent)]
     // NOTE: Apparently quadro drivers support up to 64x MSAA.
     // MSAA uses the highest 3 bits for the MSAA log2(sample count) to support up to 128x MSAA.
-    pub struct SpritePipelineKey: u32 {
+    pub struct SpritePipelineKey: u64 {
         const NONE                              = 0;
         const HDR                               = 1 << 0;
         const TONEMAP_IN_SHADER                 = 1 << 1;
         const DEBAND_DITHER                     = 1 << 2;
+        /// Simplified pipeline for sprites drawn smaller than [`SpriteExPipelineSettings::lod_threshold`]
+        const FAST_PATH                         = 1 << 3;
+        /// Blend with the state in [`SpritePipelineKey::BLEND_STATE_RESERVED_BITS`] instead of alpha blending
+        const CUSTOM_BLEND                      = 1 << 4;
+        /// Write depth, for sprites with [`SpriteAlphaMode::Mask`]
+        const ALPHA_MASK                        = 1 << 5;
+        /// Bind the noise texture of [`Dissolve`] at index 2
+        const DISSOLVE                          = 1 << 6;
+        /// Sample the layer of the [`SpriteTextureArray`](crate::SpriteTextureArray) in the
+        /// instance flags instead of the batch's image
+        const TEXTURE_ARRAY                     = 1 << 7;
+        const BLEND_STATE_RESERVED_BITS         = Self::BLEND_STATE_MASK_BITS << Self::BLEND_STATE_SHIFT_BITS;
         const MSAA_RESERVED_BITS                = Self::MSAA_MASK_BITS << Self::MSAA_SHIFT_BITS;
         const TONEMAP_METHOD_RESERVED_BITS      = Self::TONEMAP_METHOD_MASK_BITS << Self::TONEMAP_METHOD_SHIFT_BITS;
         const TONEMAP_METHOD_NONE               = 0 << Self::TONEMAP_METHOD_SHIFT_BITS;
@@ -152,17 +293,50 @@ bitflags::bitflags! {
     }
 }
 
+/// The blend factors a [`SpritePipelineKey`] can encode, indexed by their bits in the key.
+///
+/// The dual-source factors are left out, the sprite shader only has one color output.
+const BLEND_FACTORS: [BlendFactor; 13] = [
+    BlendFactor::Zero,
+    BlendFactor::One,
+    BlendFactor::Src,
+    BlendFactor::OneMinusSrc,
+    BlendFactor::SrcAlpha,
+    BlendFactor::OneMinusSrcAlpha,
+    BlendFactor::Dst,
+    BlendFactor::OneMinusDst,
+    BlendFactor::DstAlpha,
+    BlendFactor::OneMinusDstAlpha,
+    BlendFactor::SrcAlphaSaturated,
+    BlendFactor::Constant,
+    BlendFactor::OneMinusConstant,
+];
+
+/// The blend operations a [`SpritePipelineKey`] can encode, indexed by their bits in the key.
+const BLEND_OPERATIONS: [BlendOperation; 5] = [
+    BlendOperation::Add,
+    BlendOperation::Subtract,
+    BlendOperation::ReverseSubtract,
+    BlendOperation::Min,
+    BlendOperation::Max,
+];
+
 impl SpritePipelineKey {
-    const MSAA_MASK_BITS: u32 = 0b111;
-    const MSAA_SHIFT_BITS: u32 = 32 - Self::MSAA_MASK_BITS.count_ones();
-    const TONEMAP_METHOD_MASK_BITS: u32 = 0b111;
-    const TONEMAP_METHOD_SHIFT_BITS: u32 =
-        Self::MSAA_SHIFT_BITS - Self::TONEMAP_METHOD_MASK_BITS.count_ones();
+    const MSAA_MASK_BITS: u64 = 0b111;
+    const MSAA_SHIFT_BITS: u64 = 64 - Self::MSAA_MASK_BITS.count_ones() as u64;
+    const TONEMAP_METHOD_MASK_BITS: u64 = 0b111;
+    const TONEMAP_METHOD_SHIFT_BITS: u64 =
+        Self::MSAA_SHIFT_BITS - Self::TONEMAP_METHOD_MASK_BITS.count_ones() as u64;
+    /// Source factor, destination factor and operation of a blend component
+    const BLEND_COMPONENT_BITS: u64 = 5 + 5 + 3;
+    /// The color and alpha components of a blend state
+    const BLEND_STATE_MASK_BITS: u64 = (1 << (2 * Self::BLEND_COMPONENT_BITS)) - 1;
+    const BLEND_STATE_SHIFT_BITS: u64 = 8;
 
     #[inline]
     pub const fn from_msaa_samples(msaa_samples: u32) -> Self {
         let msaa_bits =
-            (msaa_samples.trailing_zeros() & Self::MSAA_MASK_BITS) << Self::MSAA_SHIFT_BITS;
+            (msaa_samples.trailing_zeros() as u64 & Self::MSAA_MASK_BITS) << Self::MSAA_SHIFT_BITS;
         Self::from_bits_retain(msaa_bits)
     }
 
@@ -171,6 +345,51 @@ impl SpritePipelineKey {
         1 << ((self.bits() >> Self::MSAA_SHIFT_BITS) & Self::MSAA_MASK_BITS)
     }
 
+    /// The key of a pipeline blending with `blend_state` instead of [`BlendState::ALPHA_BLENDING`].
+    ///
+    /// Blend states with dual-source factors such as [`BlendFactor::Src1`] can't be drawn by the
+    /// sprite shader, they give the key of alpha blending instead.
+    pub fn from_blend_state(blend_state: BlendState) -> Self {
+        let encode_component = |component: BlendComponent| {
+            let index = |factor| {
+                BLEND_FACTORS
+                    .iter()
+                    .position(|candidate| *candidate == factor)
+                    .map(|index| index as u64)
+            };
+            let operation = BLEND_OPERATIONS
+                .iter()
+                .position(|candidate| *candidate == component.operation)
+                .unwrap_or_default() as u64;
+            Some(index(component.src_factor)? | index(component.dst_factor)? << 5 | operation << 10)
+        };
+        let (Some(color), Some(alpha)) = (
+            encode_component(blend_state.color),
+            encode_component(blend_state.alpha),
+        ) else {
+            return Self::NONE;
+        };
+        let bits = color | alpha << Self::BLEND_COMPONENT_BITS;
+        Self::CUSTOM_BLEND | Self::from_bits_retain(bits << Self::BLEND_STATE_SHIFT_BITS)
+    }
+
+    /// The blend state of pipelines with this key.
+    pub fn blend_state(&self) -> BlendState {
+        if !self.contains(Self::CUSTOM_BLEND) {
+            return BlendState::ALPHA_BLENDING;
+        }
+        let bits = (self.bits() >> Self::BLEND_STATE_SHIFT_BITS) & Self::BLEND_STATE_MASK_BITS;
+        let decode_component = |bits: u64| BlendComponent {
+            src_factor: BLEND_FACTORS[(bits & 0b11111) as usize % BLEND_FACTORS.len()],
+            dst_factor: BLEND_FACTORS[(bits >> 5 & 0b11111) as usize % BLEND_FACTORS.len()],
+            operation: BLEND_OPERATIONS[(bits >> 10 & 0b111) as usize % BLEND_OPERATIONS.len()],
+        };
+        BlendState {
+            color: decode_component(bits),
+            alpha: decode_component(bits >> Self::BLEND_COMPONENT_BITS),
+        }
+    }
+
     #[inline]
     pub const fn from_hdr(hdr: bool) -> Self {
         if hdr {
@@ -185,7 +404,31 @@ impl SpecializedRenderPipeline for SpriteExPipeline {
     type Key = SpritePipelineKey;
 
     fn specialize(&self, key: Self::Key) -> RenderPipelineDescriptor {
-        let mut shader_defs = Vec::new();
+        let mut shader_defs = vec!["SPRITE_TILING".into()];
+        let texture_array = key.contains(SpritePipelineKey::TEXTURE_ARRAY);
+        if texture_array {
+            shader_defs.push("SPRITE_TEXTURE_ARRAY".into());
+        }
+        if !key.contains(SpritePipelineKey::FAST_PATH) {
+            if !texture_array {
+                shader_defs.push("SPRITE_ROTSPRITE".into());
+            }
+            shader_defs.extend([
+                "SPRITE_BORDER".into(),
+                "SPRITE_POSTERIZE".into(),
+                "SPRITE_HSV_SHIFT".into(),
+            ]);
+        }
+        let image_layout = match texture_array {
+            true => self.texture_array_layout.clone(),
+            false => self.material_layout.clone(),
+        };
+        let mut layout = vec![self.view_layout.clone(), image_layout];
+        if key.contains(SpritePipelineKey::DISSOLVE) {
+            shader_defs.push("SPRITE_DISSOLVE".into());
+            // The noise texture is bound like the sprite's image
+            layout.push(self.material_layout.clone());
+        }
         if key.contains(SpritePipelineKey::TONEMAP_IN_SHADER) {
             shader_defs.push("TONEMAP_IN_SHADER".into());
             shader_defs.push(ShaderDefVal::UInt(
@@ -230,7 +473,7 @@ impl SpecializedRenderPipeline for SpriteExPipeline {
         };
 
         let instance_rate_vertex_buffer_layout = VertexBufferLayout {
-            array_stride: 80,
+            array_stride: std::mem::size_of::<SpriteInstance>() as u64,
             step_mode: VertexStepMode::Instance,
             attributes: vec![
                 // @location(0) i_model_transpose_col0: vec4<f32>,
@@ -263,37 +506,121 @@ impl SpecializedRenderPipeline for SpriteExPipeline {
                     offset: 64,
                     shader_location: 4,
                 },
+                // @location(5) i_tiling: vec2<f32>,
+                VertexAttribute {
+                    format: VertexFormat::Float32x2,
+                    offset: 80,
+                    shader_location: 5,
+                },
+                // @location(6) i_border_width: vec2<f32>,
+                VertexAttribute {
+                    format: VertexFormat::Float32x2,
+                    offset: 88,
+                    shader_location: 6,
+                },
+                // @location(7) i_border_color: vec4<f32>,
+                VertexAttribute {
+                    format: VertexFormat::Float32x4,
+                    offset: 96,
+                    shader_location: 7,
+                },
+                // @location(8) i_flags: u32,
+                VertexAttribute {
+                    format: VertexFormat::Uint32,
+                    offset: 112,
+                    shader_location: 8,
+                },
+                // @location(9) i_posterize_levels: f32,
+                VertexAttribute {
+                    format: VertexFormat::Float32,
+                    offset: 116,
+                    shader_location: 9,
+                },
+                // @location(10) i_color_key: vec4<f32>,
+                VertexAttribute {
+                    format: VertexFormat::Float32x4,
+                    offset: 120,
+                    shader_location: 10,
+                },
+                // @location(11) i_alpha_cutoff: f32,
+                VertexAttribute {
+                    format: VertexFormat::Float32,
+                    offset: 136,
+                    shader_location: 11,
+                },
+                // @location(12) i_hsv_shift: vec3<f32>,
+                VertexAttribute {
+                    format: VertexFormat::Float32x3,
+                    offset: 140,
+                    shader_location: 12,
+                },
+                // @location(13) i_desaturate: f32,
+                VertexAttribute {
+                    format: VertexFormat::Float32,
+                    offset: 152,
+                    shader_location: 13,
+                },
+                // @location(14) i_dissolve: vec2<f32>,
+                VertexAttribute {
+                    format: VertexFormat::Float32x2,
+                    offset: 156,
+                    shader_location: 14,
+                },
+                // @location(15) i_dissolve_edge_color: vec4<f32>,
+                VertexAttribute {
+                    format: VertexFormat::Float32x4,
+                    offset: 164,
+                    shader_location: 15,
+                },
             ],
         };
 
         RenderPipelineDescriptor {
             vertex: VertexState {
-                shader: SPRITE_SHADER_HANDLE,
+                shader: self.shader.clone(),
                 entry_point: "vertex".into(),
                 shader_defs: shader_defs.clone(),
                 buffers: vec![instance_rate_vertex_buffer_layout],
             },
             fragment: Some(FragmentState {
-                shader: SPRITE_SHADER_HANDLE,
+                shader: self.shader.clone(),
                 shader_defs,
                 entry_point: "fragment".into(),
                 targets: vec![Some(ColorTargetState {
                     format,
-                    blend: Some(BlendState::ALPHA_BLENDING),
+                    blend: Some(key.blend_state()),
                     write_mask: ColorWrites::ALL,
                 })],
             }),
-            layout: vec![self.view_layout.clone(), self.material_layout.clone()],
+            layout,
             primitive: PrimitiveState {
                 front_face: FrontFace::Ccw,
-                cull_mode: None,
+                cull_mode: self.cull_mode,
                 unclipped_depth: false,
                 polygon_mode: PolygonMode::Fill,
                 conservative: false,
                 topology: PrimitiveTopology::TriangleList,
                 strip_index_format: None,
             },
-            depth_stencil: None,
+            // Sprites are transparent, they are tested against the depth buffer without writing
+            // to it unless asked to or their transparent fragments are discarded
+            depth_stencil: self.depth_format.map(|format| DepthStencilState {
+                format,
+                depth_write_enabled: self.depth_write
+                    || key.contains(SpritePipelineKey::ALPHA_MASK),
+                depth_compare: CompareFunction::GreaterEqual,
+                stencil: StencilState {
+                    front: StencilFaceState::IGNORE,
+                    back: StencilFaceState::IGNORE,
+                    read_mask: 0,
+                    write_mask: 0,
+                },
+                bias: DepthBiasState {
+                    constant: 0,
+                    slope_scale: 0.0,
+                    clamp: 0.0,
+                },
+            }),
             multisample: MultisampleState {
                 count: key.msaa_samples(),
                 mask: !0,
@@ -305,6 +632,42 @@ impl SpecializedRenderPipeline for SpriteExPipeline {
     }
 }
 
+/// Specializes [`SpriteExPipeline`] with the fragment shader of a [`SpriteFragmentShader`].
+#[derive(Resource)]
+pub struct SpriteFragmentShaderPipeline {
+    pub sprite_pipeline: SpriteExPipeline,
+}
+
+impl FromWorld for SpriteFragmentShaderPipeline {
+    fn from_world(world: &mut World) -> Self {
+        Self {
+            sprite_pipeline: world.resource::<SpriteExPipeline>().clone(),
+        }
+    }
+}
+
+impl SpecializedRenderPipeline for SpriteFragmentShaderPipeline {
+    type Key = (SpritePipelineKey, AssetId<Shader>);
+
+    fn specialize(&self, (key, shader): Self::Key) -> RenderPipelineDescriptor {
+        let mut descriptor = self.sprite_pipeline.specialize(key);
+        if let Some(fragment) = &mut descriptor.fragment {
+            fragment.shader = Handle::Weak(shader);
+        }
+        descriptor
+    }
+}
+
+/// The [`Dissolve`] of an [`ExtractedSprite`].
+#[derive(Clone, Copy)]
+pub struct ExtractedDissolve {
+    pub noise: AssetId<Image>,
+    pub progress: f32,
+    pub edge_width: f32,
+    pub edge_color: LinearRgba,
+}
+
+#[derive(Clone, Copy)]
 pub struct ExtractedSprite {
     pub transform: GlobalTransform,
     pub color: LinearRgba,
@@ -318,14 +681,202 @@ pub struct ExtractedSprite {
     pub flip_x: bool,
     pub flip_y: bool,
     pub anchor: Vec2,
+    /// Tile the texture along each axis instead of stretching it
+    pub repeat: BVec2,
+    pub sampling: SpriteSampling,
+    /// Clip rect in viewport pixels, see [`ScissorRect`]
+    pub scissor: Option<URect>,
+    pub border: Option<Border>,
+    /// Number of levels per color channel, `0` if the sprite isn't posterized
+    pub posterize_levels: u32,
+    pub hsv_shift: Option<HsvShift>,
+    /// Blend factor towards grayscale, see [`SpriteEx::desaturate`]
+    pub desaturate: f32,
+    pub dissolve: Option<ExtractedDissolve>,
+    pub color_key: Option<ColorKey>,
+    pub alpha_mode: SpriteAlphaMode,
+    /// Blend state replacing alpha blending, see [`CustomBlendState`]
+    pub blend_state: Option<BlendState>,
+    /// Fragment shader replacing the one of the pipeline, see [`SpriteFragmentShader`]
+    pub fragment_shader: Option<AssetId<Shader>>,
+    /// The sampler of the sprite's [`SpriteSampler`], if it has one
+    pub sampler: Option<SpriteSamplerId>,
+    /// The material this sprite is drawn with, see
+    /// [`SpriteExMaterialPlugin`](crate::SpriteExMaterialPlugin)
+    pub material: Option<UntypedAssetId>,
+    /// Start a new batch at this sprite, see [`BatchBreak`]
+    pub batch_break: bool,
+    /// Added to the sort key, see [`SortBias`]
+    pub sort_bias: f32,
     /// For cases where additional [`ExtractedSprites`] are created during extraction, this stores the
-    /// entity that caused that creation for use in determining visibility.
+    /// entity that caused that creation, see [`ExtractedSprites::insert_derived`].
     pub original_entity: Option<Entity>,
 }
 
+impl ExtractedSprite {
+    /// Adds the bits of this sprite's blend state, alpha mode and dissolve to the pipeline key `key`.
+    pub fn pipeline_key(
+        &self,
+        key: SpritePipelineKey,
+        settings: &SpriteExPipelineSettings,
+    ) -> SpritePipelineKey {
+        let mut key = key;
+        if let Some(blend_state) = self.blend_state {
+            key |= SpritePipelineKey::from_blend_state(blend_state);
+        }
+        if settings.depth_format.is_some() && matches!(self.alpha_mode, SpriteAlphaMode::Mask(_)) {
+            key |= SpritePipelineKey::ALPHA_MASK;
+        }
+        if self.dissolve.is_some() {
+            key |= SpritePipelineKey::DISSOLVE;
+        }
+        key
+    }
+}
+
+/// Identifies a sampler of [`SpriteSamplers`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct SpriteSamplerId(u32);
+
+/// The samplers of the [`SpriteSampler`]s of extracted sprites, shared by all sprites with the
+/// same descriptor.
+#[derive(Resource, Default)]
+pub struct SpriteSamplers {
+    descriptors: Vec<ImageSamplerDescriptor>,
+    /// The samplers created so far, in the order of `descriptors`
+    samplers: Vec<Sampler>,
+}
+
+impl SpriteSamplers {
+    /// Returns the id of the sampler described by `descriptor`, adding it if it doesn't exist yet.
+    /// The sampler is created when the sprites are prepared.
+    pub fn id(&mut self, descriptor: &ImageSamplerDescriptor) -> SpriteSamplerId {
+        let wgpu_descriptor = descriptor.as_wgpu();
+        let index = match self
+            .descriptors
+            .iter()
+            .position(|existing| existing.as_wgpu() == wgpu_descriptor)
+        {
+            Some(index) => index,
+            None => {
+                self.descriptors.push(descriptor.clone());
+                self.descriptors.len() - 1
+            }
+        };
+        SpriteSamplerId(index as u32)
+    }
+
+    /// Returns the sampler `id`, once it is created.
+    pub fn get(&self, id: SpriteSamplerId) -> Option<&Sampler> {
+        self.samplers.get(id.0 as usize)
+    }
+
+    fn create_samplers(&mut self, render_device: &RenderDevice) {
+        for descriptor in &self.descriptors[self.samplers.len()..] {
+            self.samplers
+                .push(render_device.create_sampler(&descriptor.as_wgpu()));
+        }
+    }
+}
+
 #[derive(Resource, Default)]
 pub struct ExtractedSprites {
-    pub sprites: EntityHashMap<ExtractedSprite>,
+    /// The sprites of the frame, in the order they are queued in. Sprites with equal sort keys are
+    /// drawn in this order, see [`sort_extracted_sprites`].
+    pub sprites: IndexMap<Entity, ExtractedSprite, EntityHash>,
+}
+
+impl ExtractedSprites {
+    /// Adds `sprite` as derived from `original_entity`, e.g. by a third-party extract system adding
+    /// a shadow or an outline to a sprite, and returns the render world entity spawned for it.
+    ///
+    /// A derived sprite follows its original entity: it is only drawn in views where the original
+    /// entity is visible, and it is sorted at the depth of the original entity when that entity is
+    /// an extracted sprite. At the same depth, it is drawn after its original entity and the sprites
+    /// derived from it before, so use a [`SortBias`] to draw it below. Deriving from a derived sprite
+    /// refers to its original entity instead.
+    ///
+    /// Call this after [`SpriteSystem::ExtractSprites`](crate::SpriteSystem::ExtractSprites), which
+    /// clears the extracted sprites of the previous frame.
+    pub fn insert_derived(
+        &mut self,
+        commands: &mut Commands,
+        original_entity: Entity,
+        mut sprite: ExtractedSprite,
+    ) -> Entity {
+        let original_entity = self
+            .sprites
+            .get(&original_entity)
+            .and_then(|original| original.original_entity)
+            .unwrap_or(original_entity);
+        sprite.original_entity = Some(original_entity);
+        let entity = commands.spawn_empty().id();
+        self.sprites.insert(entity, sprite);
+        entity
+    }
+
+    /// The sprites drawn in a view with the main world entities in `view_entities`, see
+    /// [`extend_view_entities`].
+    ///
+    /// Derived sprites are visible wherever their original entity is, whether or not their own
+    /// entity is.
+    pub fn iter_visible<'a>(
+        &'a self,
+        view_entities: &'a FixedBitSet,
+    ) -> impl Iterator<Item = (&'a Entity, &'a ExtractedSprite)> {
+        self.sprites.iter().filter(|(entity, sprite)| {
+            let entity = sprite.original_entity.unwrap_or(**entity);
+            view_entities.contains(entity.index() as usize)
+        })
+    }
+
+    /// The key `sprite` is sorted by with the other items of the [`Transparent2d`] phase.
+    ///
+    /// Derived sprites are sorted like their original sprite.
+    pub fn sort_key(
+        &self,
+        sprite: &ExtractedSprite,
+        sort_bias: f32,
+        sort_mode: SpriteSortMode,
+    ) -> FloatOrd {
+        let original = sprite
+            .original_entity
+            .and_then(|original_entity| self.sprites.get(&original_entity))
+            .unwrap_or(sprite);
+        let translation = original.transform.translation();
+        let depth = match sort_mode {
+            SpriteSortMode::Z => translation.z,
+            SpriteSortMode::Y => -translation.y,
+            SpriteSortMode::ZLayersThenY => {
+                // Squash the y into the fraction of the layer, keeping most precision near the
+                // origin
+                const SCALE: f32 = 1024.0;
+                let y = translation.y;
+                translation.z.floor() + 0.5 - 0.5 * y / (y.abs() + SCALE)
+            }
+        };
+        // The sprite's own bias, so derived sprites can be drawn below their original one
+        FloatOrd(depth + sort_bias + sprite.sort_bias)
+    }
+}
+
+/// Adds the indices of the main world entities of `visible_entities` matching `F` to
+/// `view_entities`, for [`ExtractedSprites::iter_visible`].
+pub fn extend_view_entities<F: 'static>(
+    view_entities: &mut FixedBitSet,
+    visible_entities: &VisibleEntities,
+) {
+    view_entities.extend(visible_entities.iter::<F>().map(|e| e.index() as usize));
+}
+
+/// Sorts the [`ExtractedSprites`] by the main world entity they are drawn for, so sprites with equal
+/// sort keys are drawn in the same order every frame instead of the order they were extracted in.
+///
+/// Derived sprites keep the order they were inserted in after their original entity.
+pub fn sort_extracted_sprites(mut extracted_sprites: ResMut<ExtractedSprites>) {
+    extracted_sprites
+        .sprites
+        .sort_by_cached_key(|entity, sprite| sprite.original_entity.unwrap_or(*entity));
 }
 
 #[derive(Resource, Default)]
@@ -345,7 +896,9 @@ pub fn extract_sprite_events(
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn extract_sprites(
+    mut commands: Commands,
     mut extracted_sprites: ResMut<ExtractedSprites>,
     sprite_query: Extract<
         Query<(
@@ -354,48 +907,277 @@ pub fn extract_sprites(
             &SpriteEx,
             &GlobalTransform,
             &Handle<Image>,
+            Option<&ScissorRect>,
+            Option<&Border>,
+            (Option<&ExtractOffset>, Option<&SortBias>),
+            Option<&FadeIn>,
+            Option<&FadeOut>,
+            (Option<&Posterize>, Option<&HsvShift>, Option<&Dissolve>),
+            Has<BatchBreak>,
+            Option<&TintStack>,
+            Option<&ComputedSlices>,
+            (
+                Option<&CustomBlendState>,
+                Option<&SpriteFragmentShader>,
+                Option<&SpriteSampler>,
+            ),
         )>,
     >,
+    mut samplers: ResMut<SpriteSamplers>,
+    images: Extract<Res<Assets<Image>>>,
+    grid: Extract<Option<Res<SpriteGrid>>>,
+    parents: Extract<Query<&Parent>>,
+    group_opacities: Extract<Query<&GroupOpacity>>,
+    inherited_tints: Extract<Query<&InheritedTint>>,
 ) {
     extracted_sprites.sprites.clear();
-    for (entity, view_visibility, sprite, transform, handle) in sprite_query.iter() {
+    // Walking up the hierarchy of every sprite is skipped while nothing is inherited
+    let inherits = !group_opacities.is_empty() || !inherited_tints.is_empty();
+    // Only consider the sprites near a camera when they are known
+    let candidates = grid.as_ref().and_then(|grid| grid.candidates());
+    let all_sprites = candidates
+        .is_none()
+        .then(|| sprite_query.iter())
+        .into_iter()
+        .flatten();
+    let near_sprites = sprite_query.iter_many(candidates.unwrap_or_default());
+    for (
+        entity,
+        view_visibility,
+        sprite,
+        transform,
+        handle,
+        scissor,
+        border,
+        (offset, sort_bias),
+        fade_in,
+        fade_out,
+        (posterize, hsv_shift, dissolve),
+        batch_break,
+        tint_stack,
+        slices,
+        (blend_state, fragment_shader, sampler),
+    ) in all_sprites.chain(near_sprites)
+    {
         if !view_visibility.get() {
             continue;
         }
 
-        let rect = sprite.rect;
+        let rect = match sprite.rect_space {
+            RectSpace::Pixels => sprite.rect,
+            // Normalized rects need the image size, so they are only resolved once the image is loaded
+            RectSpace::Normalized => images
+                .get(handle)
+                .and_then(|image| sprite.texel_rect(image.size_f32())),
+        };
+
+        let transform = match offset {
+            Some(offset) => {
+                let mut affine = transform.affine();
+                affine.translation += Vec3A::from(offset.0);
+                GlobalTransform::from(affine)
+            }
+            None => *transform,
+        };
+
+        let mut color = LinearRgba::from(sprite.color);
+        if let Some(tint_stack) = tint_stack {
+            color = tint_stack.apply(color);
+        }
+        let intensity = 1.0 + sprite.emissive.max(0.0);
+        color = LinearRgba {
+            red: color.red * intensity,
+            green: color.green * intensity,
+            blue: color.blue * intensity,
+            ..color
+        };
+        color.alpha *=
+            fade_in.map_or(1.0, FadeIn::opacity) * fade_out.map_or(1.0, FadeOut::opacity);
+        if inherits {
+            for ancestor in std::iter::once(entity).chain(parents.iter_ancestors(entity)) {
+                if let Ok(opacity) = group_opacities.get(ancestor) {
+                    color.alpha *= opacity.0.clamp(0.0, 1.0);
+                }
+                if let Ok(tint) = inherited_tints.get(ancestor) {
+                    color =
+                        LinearRgba::from_vec4(color.to_vec4() * LinearRgba::from(tint.0).to_vec4());
+                }
+            }
+        }
 
         // PERF: we don't check in this function that the `Image` asset is ready, since it should be in most cases and hashing the handle is expensive
-        extracted_sprites.sprites.insert(
-            entity,
-            ExtractedSprite {
-                color: sprite.color.into(),
-                transform: *transform,
-                rect,
-                // Pass the custom size
-                custom_size: sprite.custom_size,
-                flip_x: sprite.flip_x,
-                flip_y: sprite.flip_y,
-                image_handle_id: handle.id(),
-                anchor: sprite.anchor.as_vec(),
-                original_entity: None,
-            },
+        let extracted_sprite = ExtractedSprite {
+            color,
+            transform,
+            rect,
+            // Pass the custom size
+            custom_size: sprite.custom_size,
+            flip_x: sprite.flip_x,
+            flip_y: sprite.flip_y,
+            image_handle_id: handle.id(),
+            anchor: sprite.flipped_anchor(),
+            repeat: sprite.repeat,
+            sampling: sprite.sampling,
+            scissor: scissor.map(|scissor| scissor.0),
+            border: border.copied(),
+            posterize_levels: posterize.map_or(0, |posterize| posterize.levels),
+            hsv_shift: hsv_shift.copied(),
+            desaturate: sprite.desaturate.clamp(0.0, 1.0),
+            dissolve: dissolve.map(|dissolve| ExtractedDissolve {
+                noise: dissolve.noise.id(),
+                progress: dissolve.progress,
+                edge_width: dissolve.edge_width.max(0.0),
+                edge_color: dissolve.edge_color.into(),
+            }),
+            color_key: sprite.color_key,
+            alpha_mode: sprite.alpha_mode,
+            blend_state: blend_state.map(|blend_state| blend_state.0),
+            fragment_shader: fragment_shader.map(|shader| shader.0.id()),
+            sampler: sampler.map(|sampler| samplers.id(&sampler.0)),
+            material: None,
+            batch_break,
+            sort_bias: sort_bias.map_or(0.0, |sort_bias| sort_bias.0),
+            original_entity: None,
+        };
+
+        let Some(slices) = slices else {
+            extracted_sprites.sprites.insert(entity, extracted_sprite);
+            continue;
+        };
+        // Sliced sprites are drawn as one derived sprite per slice
+        let flip = Vec2::new(
+            if sprite.flip_x { -1.0 } else { 1.0 },
+            if sprite.flip_y { -1.0 } else { 1.0 },
         );
+        for slice in slices.slices() {
+            let mut affine = transform.affine();
+            affine.translation += affine.matrix3 * Vec3A::from((slice.offset * flip).extend(0.0));
+            extracted_sprites.insert_derived(
+                &mut commands,
+                entity,
+                ExtractedSprite {
+                    transform: affine.into(),
+                    rect: Some(slice.texture_rect),
+                    custom_size: Some(slice.draw_size),
+                    anchor: slices.slice_anchor(slice, extracted_sprite.anchor),
+                    repeat: BVec2::FALSE,
+                    ..extracted_sprite
+                },
+            );
+        }
     }
 }
 
+bitflags::bitflags! {
+    /// Per-instance flags read by `sprite.wgsl`, mirroring the `SPRITE_FLAGS_*` constants there.
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+    #[repr(transparent)]
+    pub struct SpriteInstanceFlags: u32 {
+        const NONE                              = 0;
+        const ROTSPRITE                         = 1 << 0;
+        const COLOR_KEY                         = 1 << 1;
+        /// The layer of the [`SpriteTextureArray`](crate::SpriteTextureArray) to sample
+        const TEXTURE_ARRAY_LAYER_RESERVED_BITS = u32::MAX << Self::TEXTURE_ARRAY_LAYER_SHIFT_BITS;
+    }
+}
+
+impl SpriteInstanceFlags {
+    const TEXTURE_ARRAY_LAYER_SHIFT_BITS: u32 = 16;
+
+    /// These flags sampling `layer` of the [`SpriteTextureArray`](crate::SpriteTextureArray).
+    #[inline]
+    pub const fn with_texture_array_layer(self, layer: u32) -> Self {
+        Self::from_bits_retain(self.bits() | layer << Self::TEXTURE_ARRAY_LAYER_SHIFT_BITS)
+    }
+
+    fn from_extracted(sprite: &ExtractedSprite) -> Self {
+        let mut flags = SpriteInstanceFlags::NONE;
+        if sprite.sampling == SpriteSampling::RotSprite {
+            flags |= SpriteInstanceFlags::ROTSPRITE;
+        }
+        if sprite.color_key.is_some() {
+            flags |= SpriteInstanceFlags::COLOR_KEY;
+        }
+        flags
+    }
+}
+
+/// The per instance vertex data of a sprite quad, see [`SpriteMeta::push_instance`].
 #[repr(C)]
 #[derive(Copy, Clone, Pod, Zeroable)]
-struct SpriteInstance {
+pub struct SpriteInstance {
     // Affine 4x3 transposed to 3x4
     pub i_model_transpose: [Vec4; 3],
     pub i_color: [f32; 4],
     pub i_uv_offset_scale: [f32; 4],
+    pub i_tiling: [f32; 2],
+    pub i_border_width: [f32; 2],
+    pub i_border_color: [f32; 4],
+    pub i_flags: u32,
+    pub i_posterize_levels: f32,
+    pub i_color_key: [f32; 4],
+    pub i_alpha_cutoff: f32,
+    pub i_hsv_shift: [f32; 3],
+    pub i_desaturate: f32,
+    pub i_dissolve: [f32; 2],
+    pub i_dissolve_edge_color: [f32; 4],
+    _padding: [u32; 3],
+}
+
+/// The parameters of a [`SpriteInstance`] besides its transform, see [`SpriteInstance::from`].
+///
+/// The default draws the whole texture once, without any effect.
+#[derive(Clone, Copy, Debug)]
+pub struct SpriteInstanceParams {
+    pub color: LinearRgba,
+    /// The texture region, with its offset in `xy` and its scale in `zw`
+    pub uv_offset_scale: Vec4,
+    /// The number of times the texture region repeats along each axis
+    pub tiling: Vec2,
+    /// The border thickness as a fraction of the quad size along each axis
+    pub border_width: Vec2,
+    pub border_color: LinearRgba,
+    /// `0` to leave the quad unposterized
+    pub posterize_levels: u32,
+    /// The key color in `xyz` and the tolerance in `w` of quads flagged with
+    /// [`SpriteInstanceFlags::COLOR_KEY`]
+    pub color_key: Vec4,
+    /// Fragments with an alpha below it are discarded
+    pub alpha_cutoff: f32,
+    /// The hue rotation in turns, and the saturation and value factors
+    pub hsv_shift: Vec3,
+    /// Blends the final color towards grayscale
+    pub desaturate: f32,
+    /// The progress and edge width of a [`Dissolve`]
+    pub dissolve: Vec2,
+    pub dissolve_edge_color: LinearRgba,
+    pub flags: SpriteInstanceFlags,
+}
+
+impl Default for SpriteInstanceParams {
+    fn default() -> Self {
+        Self {
+            color: LinearRgba::WHITE,
+            uv_offset_scale: Vec4::new(0.0, 1.0, 1.0, -1.0),
+            tiling: Vec2::ONE,
+            border_width: Vec2::ZERO,
+            border_color: LinearRgba::NONE,
+            posterize_levels: 0,
+            color_key: Vec4::ZERO,
+            alpha_cutoff: 0.0,
+            hsv_shift: Vec3::new(0.0, 1.0, 1.0),
+            desaturate: 0.0,
+            dissolve: Vec2::ZERO,
+            dissolve_edge_color: LinearRgba::NONE,
+            flags: SpriteInstanceFlags::NONE,
+        }
+    }
 }
 
 impl SpriteInstance {
+    /// Creates the instance of the unit quad from `(0, 0)` to `(1, 1)` transformed by `transform`.
     #[inline]
-    fn from(transform: &Affine3A, color: &LinearRgba, uv_offset_scale: &Vec4) -> Self {
+    pub fn from(transform: &Affine3A, params: &SpriteInstanceParams) -> Self {
         let transpose_model_3x3 = transform.matrix3.transpose();
         Self {
             i_model_transpose: [
@@ -403,8 +1185,20 @@ impl SpriteInstance {
                 transpose_model_3x3.y_axis.extend(transform.translation.y),
                 transpose_model_3x3.z_axis.extend(transform.translation.z),
             ],
-            i_color: color.to_f32_array(),
-            i_uv_offset_scale: uv_offset_scale.to_array(),
+            i_color: params.color.to_f32_array(),
+            i_uv_offset_scale: params.uv_offset_scale.to_array(),
+            i_tiling: params.tiling.to_array(),
+            i_border_width: params.border_width.to_array(),
+            i_border_color: params.border_color.to_f32_array(),
+            i_flags: params.flags.bits(),
+            i_posterize_levels: params.posterize_levels as f32,
+            i_color_key: params.color_key.to_array(),
+            i_alpha_cutoff: params.alpha_cutoff,
+            i_hsv_shift: params.hsv_shift.to_array(),
+            i_desaturate: params.desaturate,
+            i_dissolve: params.dissolve.to_array(),
+            i_dissolve_edge_color: params.dissolve_edge_color.to_f32_array(),
+            _padding: [0; 3],
         }
     }
 }
@@ -415,6 +1209,27 @@ pub struct SpriteMeta {
     sprite_instance_buffer: RawBufferVec<SpriteInstance>,
 }
 
+impl SpriteMeta {
+    /// Adds an instance to the instance buffer of the current frame and returns its index, to be
+    /// drawn through the [`SpriteBatch::range`] of a custom phase item.
+    ///
+    /// Call this between [`prepare_sprite_image_bind_groups`], which clears the buffer every
+    /// frame, and [`write_sprite_buffers`], which uploads it.
+    pub fn push_instance(&mut self, instance: SpriteInstance) -> u32 {
+        self.sprite_instance_buffer.push(instance) as u32
+    }
+
+    /// The number of instances in the instance buffer of the current frame.
+    pub fn instance_count(&self) -> u32 {
+        self.sprite_instance_buffer.len() as u32
+    }
+
+    /// The instances of the current frame.
+    pub(crate) fn instances(&self) -> &[SpriteInstance] {
+        self.sprite_instance_buffer.values()
+    }
+}
+
 impl Default for SpriteMeta {
     fn default() -> Self {
         Self {
@@ -429,15 +1244,196 @@ pub struct SpriteViewBindGroup {
     pub value: BindGroup,
 }
 
+/// A range of instances drawn by [`DrawSprite`] for the phase item of the entity holding it.
 #[derive(Component, PartialEq, Eq, Clone)]
 pub struct SpriteBatch {
-    image_handle_id: AssetId<Image>,
-    range: Range<u32>,
+    /// The image the quads are textured with, see [`ImageBindGroups::prepare_image`]
+    pub image_handle_id: AssetId<Image>,
+    /// Clip rect in viewport pixels, see [`ScissorRect`]
+    pub scissor: Option<URect>,
+    /// Whether the batch's quads are mirrored and drawn with reversed winding
+    pub mirrored: bool,
+    /// The sampler the image is sampled with instead of its own, see [`SpriteSampler`]
+    pub sampler: Option<SpriteSamplerId>,
+    /// The noise image of the batch's [`Dissolve`], bound at index 2
+    pub dissolve_noise: Option<AssetId<Image>>,
+    /// Whether the quads sample the [`SpriteTextureArray`] instead of `image_handle_id`
+    pub texture_array: bool,
+    /// The instances in [`SpriteMeta`] to draw
+    pub range: Range<u32>,
 }
 
 #[derive(Resource, Default)]
 pub struct ImageBindGroups {
     values: HashMap<AssetId<Image>, BindGroup>,
+    /// Bind groups of removed images that sprites still referenced, see [`RetainedBindGroup`]
+    retained: HashMap<AssetId<Image>, RetainedBindGroup>,
+    /// Bound for images whose bind group hasn't been created yet, see [`SpriteBindGroupBudget`]
+    placeholder: Option<BindGroup>,
+    /// The size of the image of each bind group in `values`
+    sizes: HashMap<AssetId<Image>, Vec2>,
+    /// Bind groups of images sampled with the sampler of a [`SpriteSampler`]
+    sampled: HashMap<(AssetId<Image>, SpriteSamplerId), BindGroup>,
+}
+
+/// The bind group of an image asset that was removed, for example because the last strong handle
+/// to it was dropped, while sprites still reference it through weak handles.
+///
+/// The bind group keeps the texture alive, so these sprites keep drawing instead of flickering out
+/// for the frames it takes them to switch images. It is dropped after the first frame in which no
+/// sprite uses it.
+struct RetainedBindGroup {
+    bind_group: BindGroup,
+    size: Vec2,
+    used: bool,
+}
+
+impl ImageBindGroups {
+    /// Creates the bind group [`SpriteBatch`]es with the image `id` are drawn with, if it doesn't
+    /// exist yet.
+    ///
+    /// Bind groups of the images of extracted sprites are created by
+    /// [`prepare_sprite_image_bind_groups`], custom batches need this for other images.
+    pub fn prepare_image(
+        &mut self,
+        id: AssetId<Image>,
+        gpu_image: &GpuImage,
+        render_device: &RenderDevice,
+        sprite_pipeline: &SpriteExPipeline,
+    ) {
+        if !self.values.contains_key(&id) {
+            self.insert(
+                id,
+                create_image_bind_group(render_device, sprite_pipeline, gpu_image),
+                gpu_image.size.as_vec2(),
+            );
+        }
+    }
+
+    /// Creates the bind group of the image `id` sampled with the sampler `sampler_id`, if it
+    /// doesn't exist yet and the sampler is created.
+    fn prepare_sampled_image(
+        &mut self,
+        id: AssetId<Image>,
+        sampler_id: SpriteSamplerId,
+        gpu_image: &GpuImage,
+        samplers: &SpriteSamplers,
+        render_device: &RenderDevice,
+        sprite_pipeline: &SpriteExPipeline,
+    ) {
+        let Some(sampler) = samplers.get(sampler_id) else {
+            return;
+        };
+        self.sampled.entry((id, sampler_id)).or_insert_with(|| {
+            render_device.create_bind_group(
+                "sprite_material_bind_group",
+                &sprite_pipeline.material_layout,
+                &BindGroupEntries::sequential((&gpu_image.texture_view, sampler)),
+            )
+        });
+    }
+
+    /// Returns the bind group `batch` is drawn with.
+    fn get_batch(&self, batch: &SpriteBatch) -> Option<&BindGroup> {
+        batch
+            .sampler
+            .and_then(|sampler| self.sampled.get(&(batch.image_handle_id, sampler)))
+            .or_else(|| self.get(batch.image_handle_id))
+    }
+
+    fn insert(&mut self, id: AssetId<Image>, bind_group: BindGroup, size: Vec2) {
+        self.values.insert(id, bind_group);
+        self.sizes.insert(id, size);
+    }
+
+    fn remove(&mut self, id: AssetId<Image>) -> Option<(BindGroup, Vec2)> {
+        self.sampled.retain(|(image, _), _| *image != id);
+        let bind_group = self.values.remove(&id)?;
+        Some((bind_group, self.sizes.remove(&id).unwrap_or_default()))
+    }
+
+    fn contains(&self, id: AssetId<Image>) -> bool {
+        self.values.contains_key(&id) || self.retained.contains_key(&id)
+    }
+
+    fn get(&self, id: AssetId<Image>) -> Option<&BindGroup> {
+        self.values
+            .get(&id)
+            .or_else(|| self.retained.get(&id).map(|retained| &retained.bind_group))
+    }
+}
+
+fn create_image_bind_group(
+    render_device: &RenderDevice,
+    sprite_pipeline: &SpriteExPipeline,
+    gpu_image: &GpuImage,
+) -> BindGroup {
+    render_device.create_bind_group(
+        "sprite_material_bind_group",
+        &sprite_pipeline.material_layout,
+        &BindGroupEntries::sequential((&gpu_image.texture_view, &gpu_image.sampler)),
+    )
+}
+
+/// Creates the bind groups of the images used by visible sprites, within the frame's budget.
+/// Images used by more sprites are created first.
+fn create_budgeted_image_bind_groups(
+    budget: &SpriteBindGroupBudget,
+    render_device: &RenderDevice,
+    sprite_pipeline: &SpriteExPipeline,
+    image_bind_groups: &mut ImageBindGroups,
+    gpu_images: &RenderAssets<GpuImage>,
+    extracted_sprites: &ExtractedSprites,
+    phases: &ViewSortedRenderPhases<Transparent2d>,
+) {
+    let mut pending: HashMap<AssetId<Image>, usize> = HashMap::default();
+    for transparent_phase in phases.values() {
+        for item in &transparent_phase.items {
+            let Some(extracted_sprite) = extracted_sprites.sprites.get(&item.entity) else {
+                continue;
+            };
+            if !image_bind_groups.contains(extracted_sprite.image_handle_id) {
+                *pending.entry(extracted_sprite.image_handle_id).or_default() += 1;
+            }
+        }
+    }
+    if pending.is_empty() {
+        return;
+    }
+
+    image_bind_groups.placeholder.get_or_insert_with(|| {
+        create_image_bind_group(
+            render_device,
+            sprite_pipeline,
+            &sprite_pipeline.dummy_white_gpu_image,
+        )
+    });
+
+    let mut pending: Vec<_> = pending.into_iter().collect();
+    pending.sort_unstable_by(|(_, a), (_, b)| b.cmp(a));
+
+    let start = Instant::now();
+    let mut created = 0;
+    for (image_handle_id, _) in pending {
+        if budget
+            .max_count
+            .is_some_and(|max_count| created >= max_count)
+            || budget
+                .max_time
+                .is_some_and(|max_time| start.elapsed() >= max_time)
+        {
+            break;
+        }
+        let Some(gpu_image) = gpu_images.get(image_handle_id) else {
+            continue;
+        };
+        image_bind_groups.insert(
+            image_handle_id,
+            create_image_bind_group(render_device, sprite_pipeline, gpu_image),
+            gpu_image.size.as_vec2(),
+        );
+        created += 1;
+    }
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -445,75 +1441,119 @@ pub fn queue_sprites(
     mut view_entities: Local<FixedBitSet>,
     draw_functions: Res<DrawFunctions<Transparent2d>>,
     sprite_pipeline: Res<SpriteExPipeline>,
+    settings: Res<SpriteExPipelineSettings>,
+    gpu_images: Res<RenderAssets<GpuImage>>,
     mut pipelines: ResMut<SpecializedRenderPipelines<SpriteExPipeline>>,
+    shader_pipeline: Res<SpriteFragmentShaderPipeline>,
+    mut shader_pipelines: ResMut<SpecializedRenderPipelines<SpriteFragmentShaderPipeline>>,
     pipeline_cache: Res<PipelineCache>,
     msaa: Res<Msaa>,
     extracted_sprites: Res<ExtractedSprites>,
+    texture_array: Option<Res<SpriteTextureArray>>,
     mut transparent_render_phases: ResMut<ViewSortedRenderPhases<Transparent2d>>,
     mut views: Query<(
         Entity,
         &VisibleEntities,
         &ExtractedView,
+        Option<&ViewTarget>,
         Option<&Tonemapping>,
         Option<&DebandDither>,
+        Option<&SpriteLodThreshold>,
+        Option<&SpriteSortMode>,
     )>,
 ) {
-    let msaa_key = SpritePipelineKey::from_msaa_samples(msaa.samples());
-
     let draw_sprite_function = draw_functions.read().id::<DrawSprite>();
 
-    for (view_entity, visible_entities, view, tonemapping, dither) in &mut views {
+    for (
+        view_entity,
+        visible_entities,
+        view,
+        view_target,
+        tonemapping,
+        dither,
+        lod_threshold,
+        sort_mode,
+    ) in &mut views
+    {
         let Some(transparent_phase) = transparent_render_phases.get_mut(&view_entity) else {
             continue;
         };
 
-        let mut view_key = SpritePipelineKey::from_hdr(view.hdr) | msaa_key;
-
-        if !view.hdr {
-            if let Some(tonemapping) = tonemapping {
-                view_key |= SpritePipelineKey::TONEMAP_IN_SHADER;
-                view_key |= match tonemapping {
-                    Tonemapping::None => SpritePipelineKey::TONEMAP_METHOD_NONE,
-                    Tonemapping::Reinhard => SpritePipelineKey::TONEMAP_METHOD_REINHARD,
-                    Tonemapping::ReinhardLuminance => {
-                        SpritePipelineKey::TONEMAP_METHOD_REINHARD_LUMINANCE
-                    }
-                    Tonemapping::AcesFitted => SpritePipelineKey::TONEMAP_METHOD_ACES_FITTED,
-                    Tonemapping::AgX => SpritePipelineKey::TONEMAP_METHOD_AGX,
-                    Tonemapping::SomewhatBoringDisplayTransform => {
-                        SpritePipelineKey::TONEMAP_METHOD_SOMEWHAT_BORING_DISPLAY_TRANSFORM
-                    }
-                    Tonemapping::TonyMcMapface => SpritePipelineKey::TONEMAP_METHOD_TONY_MC_MAPFACE,
-                    Tonemapping::BlenderFilmic => SpritePipelineKey::TONEMAP_METHOD_BLENDER_FILMIC,
-                };
-            }
-            if let Some(DebandDither::Enabled) = dither {
-                view_key |= SpritePipelineKey::DEBAND_DITHER;
-            }
-        }
-
+        let view_key = sprite_view_key(view, view_target, tonemapping, dither, &msaa, &settings);
         let pipeline = pipelines.specialize(&pipeline_cache, &sprite_pipeline, view_key);
+        // Tiny sprites skip the per-sprite effects and tonemapping
+        let lod_threshold =
+            lod_threshold.map_or(settings.lod_threshold, |lod_threshold| lod_threshold.0);
+        let fast_path = lod_threshold.map(|threshold| {
+            let fast_key = (view_key
+                - SpritePipelineKey::TONEMAP_IN_SHADER
+                - SpritePipelineKey::DEBAND_DITHER
+                - SpritePipelineKey::TONEMAP_METHOD_RESERVED_BITS)
+                | SpritePipelineKey::FAST_PATH;
+            let clip_from_world = view.clip_from_world.unwrap_or_else(|| {
+                view.clip_from_view * view.world_from_view.compute_matrix().inverse()
+            });
+            (
+                fast_key,
+                pipelines.specialize(&pipeline_cache, &sprite_pipeline, fast_key),
+                threshold,
+                clip_from_world,
+                view.viewport.zw().as_vec2() / 2.0,
+            )
+        });
 
         view_entities.clear();
-        view_entities.extend(
-            visible_entities
-                .iter::<WithSprite>()
-                .map(|e| e.index() as usize),
-        );
+        extend_view_entities::<WithSprite>(&mut view_entities, visible_entities);
+        if settings.bevy_sprites {
+            extend_view_entities::<bevy_sprite::WithSprite>(&mut view_entities, visible_entities);
+        }
 
         transparent_phase
             .items
             .reserve(extracted_sprites.sprites.len());
 
-        for (entity, extracted_sprite) in extracted_sprites.sprites.iter() {
-            let index = extracted_sprite.original_entity.unwrap_or(*entity).index();
-
-            if !view_entities.contains(index as usize) {
+        for (entity, extracted_sprite) in extracted_sprites.iter_visible(&view_entities) {
+            // Sprites with a material are queued by the `SpriteExMaterialPlugin` of its type
+            if extracted_sprite.material.is_some() {
                 continue;
             }
 
-            // These items will be sorted by depth with other phase items
-            let sort_key = FloatOrd(extracted_sprite.transform.translation().z);
+            let sort_key = extracted_sprites.sort_key(
+                extracted_sprite,
+                settings.sort_bias,
+                sort_mode.copied().unwrap_or(settings.sort_mode),
+            );
+
+            let (key, pipeline) = match fast_path {
+                Some((fast_key, fast_pipeline, threshold, clip_from_world, half_viewport_size))
+                    if projected_size(
+                        extracted_sprite,
+                        &gpu_images,
+                        &clip_from_world,
+                        half_viewport_size,
+                    )
+                    .is_some_and(|size| size < threshold) =>
+                {
+                    (fast_key, fast_pipeline)
+                }
+                _ => (view_key, pipeline),
+            };
+            // Custom blend states, depth writes and shaders need their own pipeline, which also
+            // splits the batch
+            let mut sprite_key = extracted_sprite.pipeline_key(key, &settings);
+            if texture_array
+                .as_ref()
+                .is_some_and(|texture_array| texture_array.region(extracted_sprite).is_some())
+            {
+                sprite_key |= SpritePipelineKey::TEXTURE_ARRAY;
+            }
+            let pipeline = if let Some(shader) = extracted_sprite.fragment_shader {
+                shader_pipelines.specialize(&pipeline_cache, &shader_pipeline, (sprite_key, shader))
+            } else if sprite_key == key {
+                pipeline
+            } else {
+                pipelines.specialize(&pipeline_cache, &sprite_pipeline, sprite_key)
+            };
 
             // Add the item to the render phase
             transparent_phase.add(Transparent2d {
@@ -529,33 +1569,155 @@ pub fn queue_sprites(
     }
 }
 
+/// The pipeline key of the sprites drawn by a view, without the bits depending on each sprite.
+pub fn sprite_view_key(
+    view: &ExtractedView,
+    view_target: Option<&ViewTarget>,
+    tonemapping: Option<&Tonemapping>,
+    dither: Option<&DebandDither>,
+    msaa: &Msaa,
+    settings: &SpriteExPipelineSettings,
+) -> SpritePipelineKey {
+    // Match the sample count of the view's main texture, which can differ between views
+    let msaa_samples = view_target.map_or(msaa.samples(), |view_target| {
+        view_target
+            .sampled_main_texture()
+            .map_or(1, |texture| texture.sample_count())
+    });
+    let mut view_key =
+        SpritePipelineKey::from_hdr(view.hdr) | SpritePipelineKey::from_msaa_samples(msaa_samples);
+
+    if !view.hdr && !settings.disable_tonemapping {
+        if let Some(tonemapping) = tonemapping {
+            view_key |= SpritePipelineKey::TONEMAP_IN_SHADER;
+            view_key |= match tonemapping {
+                Tonemapping::None => SpritePipelineKey::TONEMAP_METHOD_NONE,
+                Tonemapping::Reinhard => SpritePipelineKey::TONEMAP_METHOD_REINHARD,
+                Tonemapping::ReinhardLuminance => {
+                    SpritePipelineKey::TONEMAP_METHOD_REINHARD_LUMINANCE
+                }
+                Tonemapping::AcesFitted => SpritePipelineKey::TONEMAP_METHOD_ACES_FITTED,
+                Tonemapping::AgX => SpritePipelineKey::TONEMAP_METHOD_AGX,
+                Tonemapping::SomewhatBoringDisplayTransform => {
+                    SpritePipelineKey::TONEMAP_METHOD_SOMEWHAT_BORING_DISPLAY_TRANSFORM
+                }
+                Tonemapping::TonyMcMapface => SpritePipelineKey::TONEMAP_METHOD_TONY_MC_MAPFACE,
+                Tonemapping::BlenderFilmic => SpritePipelineKey::TONEMAP_METHOD_BLENDER_FILMIC,
+            };
+        }
+        if let Some(DebandDither::Enabled) = dither {
+            view_key |= SpritePipelineKey::DEBAND_DITHER;
+        }
+    }
+    view_key
+}
+
+/// The larger side of the sprite's quad on screen in pixels, if its image is loaded.
+fn projected_size(
+    sprite: &ExtractedSprite,
+    gpu_images: &RenderAssets<GpuImage>,
+    clip_from_world: &Mat4,
+    half_viewport_size: Vec2,
+) -> Option<f32> {
+    let quad_size = match (sprite.custom_size, sprite.rect) {
+        (Some(custom_size), _) => custom_size,
+        (None, Some(rect)) => rect.size(),
+        (None, None) => gpu_images.get(sprite.image_handle_id)?.size.as_vec2(),
+    };
+    let affine = sprite.transform.affine();
+    let to_pixels = |point: Vec3A| {
+        let clip = *clip_from_world * point.extend(1.0);
+        clip.xy() / clip.w * half_viewport_size
+    };
+    let center = to_pixels(affine.translation);
+    let width = to_pixels(affine.translation + affine.matrix3.x_axis * quad_size.x) - center;
+    let height = to_pixels(affine.translation + affine.matrix3.y_axis * quad_size.y) - center;
+    Some(width.length().max(height.length()))
+}
+
+/// The [`SpriteExposure`] of each view.
+#[derive(Resource, Default)]
+pub struct SpriteExposureUniforms {
+    pub uniforms: DynamicUniformBuffer<f32>,
+}
+
+/// Offset of a view's exposure in [`SpriteExposureUniforms`].
+#[derive(Component)]
+pub struct SpriteExposureUniformOffset {
+    pub offset: u32,
+}
+
+pub fn prepare_sprite_exposure_uniforms(
+    mut commands: Commands,
+    render_device: Res<RenderDevice>,
+    render_queue: Res<RenderQueue>,
+    mut exposure_uniforms: ResMut<SpriteExposureUniforms>,
+    views: Query<(Entity, Option<&SpriteExposure>), With<ExtractedView>>,
+) {
+    let Some(mut writer) =
+        exposure_uniforms
+            .uniforms
+            .get_writer(views.iter().len(), &render_device, &render_queue)
+    else {
+        return;
+    };
+    for (entity, exposure) in &views {
+        let offset = writer.write(&exposure.copied().unwrap_or_default().0);
+        commands
+            .entity(entity)
+            .insert(SpriteExposureUniformOffset { offset });
+    }
+}
+
 #[allow(clippy::too_many_arguments)]
 pub fn prepare_sprite_view_bind_groups(
     mut commands: Commands,
     render_device: Res<RenderDevice>,
     sprite_pipeline: Res<SpriteExPipeline>,
     view_uniforms: Res<ViewUniforms>,
-    views: Query<(Entity, &Tonemapping), With<ExtractedView>>,
+    globals_buffer: Res<GlobalsBuffer>,
+    exposure_uniforms: Res<SpriteExposureUniforms>,
+    views: Query<(Entity, Option<&Tonemapping>), With<ExtractedView>>,
     tonemapping_luts: Res<TonemappingLuts>,
     images: Res<RenderAssets<GpuImage>>,
     fallback_image: Res<FallbackImage>,
 ) {
-    let Some(view_binding) = view_uniforms.uniforms.binding() else {
+    let (Some(view_binding), Some(globals_binding), Some(exposure_binding)) = (
+        view_uniforms.uniforms.binding(),
+        globals_buffer.buffer.binding(),
+        exposure_uniforms.uniforms.binding(),
+    ) else {
         return;
     };
 
     for (entity, tonemapping) in &views {
-        let lut_bindings =
-            get_lut_bindings(&images, &tonemapping_luts, tonemapping, &fallback_image);
-        let view_bind_group = render_device.create_bind_group(
-            "mesh2d_view_bind_group",
-            &sprite_pipeline.view_layout,
-            &BindGroupEntries::with_indices((
-                (0, view_binding.clone()),
-                (1, lut_bindings.0),
-                (2, lut_bindings.1),
-            )),
-        );
+        let view_bind_group = if sprite_pipeline.tonemapping {
+            // Views without tonemapping still need the LUT bindings of the layout
+            let tonemapping = tonemapping.unwrap_or(&Tonemapping::None);
+            let lut_bindings =
+                get_lut_bindings(&images, &tonemapping_luts, tonemapping, &fallback_image);
+            render_device.create_bind_group(
+                "mesh2d_view_bind_group",
+                &sprite_pipeline.view_layout,
+                &BindGroupEntries::with_indices((
+                    (0, view_binding.clone()),
+                    (1, lut_bindings.0),
+                    (2, lut_bindings.1),
+                    (3, globals_binding.clone()),
+                    (4, exposure_binding.clone()),
+                )),
+            )
+        } else {
+            render_device.create_bind_group(
+                "mesh2d_view_bind_group",
+                &sprite_pipeline.view_layout,
+                &BindGroupEntries::with_indices((
+                    (0, view_binding.clone()),
+                    (3, globals_binding.clone()),
+                    (4, exposure_binding.clone()),
+                )),
+            )
+        };
 
         commands.entity(entity).insert(SpriteViewBindGroup {
             value: view_bind_group,
@@ -568,7 +1730,6 @@ pub fn prepare_sprite_image_bind_groups(
     mut commands: Commands,
     mut previous_len: Local<usize>,
     render_device: Res<RenderDevice>,
-    render_queue: Res<RenderQueue>,
     mut sprite_meta: ResMut<SpriteMeta>,
     sprite_pipeline: Res<SpriteExPipeline>,
     mut image_bind_groups: ResMut<ImageBindGroups>,
@@ -576,15 +1737,32 @@ pub fn prepare_sprite_image_bind_groups(
     extracted_sprites: Res<ExtractedSprites>,
     mut phases: ResMut<ViewSortedRenderPhases<Transparent2d>>,
     events: Res<SpriteAssetEvents>,
+    budget: Res<SpriteBindGroupBudget>,
+    mut samplers: ResMut<SpriteSamplers>,
+    texture_array: Option<Res<SpriteTextureArray>>,
 ) {
+    samplers.create_samplers(&render_device);
+
     // If an image has changed, the GpuImage has (probably) changed
     for event in &events.images {
         match event {
             AssetEvent::Added { .. } |
             // Images don't have dependencies
             AssetEvent::LoadedWithDependencies { .. } => {}
-            AssetEvent::Unused { id } | AssetEvent::Modified { id } | AssetEvent::Removed { id } => {
-                image_bind_groups.values.remove(id);
+            AssetEvent::Modified { id } => {
+                image_bind_groups.remove(*id);
+            }
+            AssetEvent::Unused { id } | AssetEvent::Removed { id } => {
+                if let Some((bind_group, size)) = image_bind_groups.remove(*id) {
+                    image_bind_groups.retained.insert(
+                        *id,
+                        RetainedBindGroup {
+                            bind_group,
+                            size,
+                            used: false,
+                        },
+                    );
+                }
             }
         };
     }
@@ -599,10 +1777,31 @@ pub fn prepare_sprite_image_bind_groups(
 
     let image_bind_groups = &mut *image_bind_groups;
 
+    // With a budget the bind groups are created up front, in order of priority
+    let budgeted = budget.is_limited();
+    if budgeted {
+        create_budgeted_image_bind_groups(
+            &budget,
+            &render_device,
+            &sprite_pipeline,
+            image_bind_groups,
+            &gpu_images,
+            &extracted_sprites,
+            &phases,
+        );
+    }
+
     for transparent_phase in phases.values_mut() {
         let mut batch_item_index = 0;
         let mut batch_image_size = Vec2::ZERO;
         let mut batch_image_handle = AssetId::invalid();
+        let mut batch_scissor = None;
+        let mut batch_mirrored = false;
+        let mut batch_sampler = None;
+        let mut batch_pipeline = CachedRenderPipelineId::INVALID;
+        let mut batch_material = None;
+        let mut batch_dissolve_noise = None;
+        let mut batch_texture_array = false;
 
         // Iterate through the phase items and detect when successive sprites that can be batched.
         // Spawn an entity with a `SpriteBatch` component for each possible batch.
@@ -614,30 +1813,55 @@ pub fn prepare_sprite_image_bind_groups(
                 // batch to draw the other phase item(s) and to respect draw order. This can be
                 // done by invalidating the batch_image_handle
                 batch_image_handle = AssetId::invalid();
+                batch_texture_array = false;
                 continue;
             };
 
-            let batch_image_changed = batch_image_handle != extracted_sprite.image_handle_id;
-            if batch_image_changed {
-                let Some(gpu_image) = gpu_images.get(extracted_sprite.image_handle_id) else {
+            // Dissolving sprites are hidden until their noise texture is loaded
+            let dissolve_noise = extracted_sprite.dissolve.map(|dissolve| dissolve.noise);
+            if let Some(noise) = dissolve_noise {
+                let Some(gpu_image) = gpu_images.get(noise) else {
                     continue;
                 };
+                image_bind_groups.prepare_image(noise, gpu_image, &render_device, &sprite_pipeline);
+            }
 
-                batch_image_size = gpu_image.size.as_vec2();
+            // Sprites drawn from the texture array are batched regardless of their image
+            let array_region = texture_array
+                .as_ref()
+                .and_then(|texture_array| texture_array.region(extracted_sprite));
+            let batch_image_changed = match array_region {
+                Some(_) => !batch_texture_array,
+                None => {
+                    batch_texture_array || batch_image_handle != extracted_sprite.image_handle_id
+                }
+            };
+            if let Some((_, rect)) = array_region {
+                batch_image_size = rect.size().as_vec2();
                 batch_image_handle = extracted_sprite.image_handle_id;
-                image_bind_groups
-                    .values
-                    .entry(batch_image_handle)
-                    .or_insert_with(|| {
-                        render_device.create_bind_group(
-                            "sprite_material_bind_group",
-                            &sprite_pipeline.material_layout,
-                            &BindGroupEntries::sequential((
-                                &gpu_image.texture_view,
-                                &gpu_image.sampler,
-                            )),
-                        )
-                    });
+                batch_texture_array = true;
+            } else if batch_image_changed {
+                batch_texture_array = false;
+                if let Some(gpu_image) = gpu_images.get(extracted_sprite.image_handle_id) {
+                    batch_image_size = gpu_image.size.as_vec2();
+                    batch_image_handle = extracted_sprite.image_handle_id;
+                    if !budgeted && !image_bind_groups.values.contains_key(&batch_image_handle) {
+                        image_bind_groups.insert(
+                            batch_image_handle,
+                            create_image_bind_group(&render_device, &sprite_pipeline, gpu_image),
+                            batch_image_size,
+                        );
+                    }
+                } else if let Some(retained) = image_bind_groups
+                    .retained
+                    .get_mut(&extracted_sprite.image_handle_id)
+                {
+                    retained.used = true;
+                    batch_image_size = retained.size;
+                    batch_image_handle = extracted_sprite.image_handle_id;
+                } else {
+                    continue;
+                }
             }
 
             // By default, the size of the quad is the size of the texture
@@ -668,11 +1892,48 @@ pub fn prepare_sprite_image_bind_groups(
                 uv_offset_scale.y += uv_offset_scale.w;
                 uv_offset_scale.w *= -1.0;
             }
+            // Images are packed into rects of the array's layers
+            if let (Some(texture_array), Some((_, rect))) = (&texture_array, array_region) {
+                uv_offset_scale = texture_array.remap_uv_offset_scale(rect, uv_offset_scale);
+            }
+
+            // The size of the texture region, used to tile the texture when repeating
+            let texture_size = quad_size;
 
             // Override the size if a custom one is specified
             if let Some(custom_size) = extracted_sprite.custom_size {
                 quad_size = custom_size;
             }
+
+            // The number of times the texture region repeats across the quad along each axis
+            let tiling = Vec2::select(extracted_sprite.repeat, quad_size / texture_size, Vec2::ONE);
+            // The border thickness as a fraction of the quad size along each axis, kept finite for
+            // quads without an area
+            let (border_width, border_color) = match extracted_sprite.border {
+                Some(border) => (
+                    Vec2::splat(border.thickness.max(0.0))
+                        / quad_size.abs().max(Vec2::splat(f32::EPSILON)),
+                    border.color.into(),
+                ),
+                None => (Vec2::ZERO, LinearRgba::NONE),
+            };
+            let color_key = extracted_sprite.color_key.map_or(Vec4::ZERO, |color_key| {
+                LinearRgba::from(color_key.color)
+                    .to_vec3()
+                    .extend(color_key.tolerance)
+            });
+            let (dissolve, dissolve_edge_color) = match extracted_sprite.dissolve {
+                Some(dissolve) => (
+                    Vec2::new(dissolve.progress, dissolve.edge_width),
+                    dissolve.edge_color,
+                ),
+                None => (Vec2::ZERO, LinearRgba::NONE),
+            };
+            let hsv_shift = extracted_sprite
+                .hsv_shift
+                .map_or(Vec3::new(0.0, 1.0, 1.0), |shift| {
+                    Vec3::new(shift.hue / 360.0, shift.saturation, shift.value)
+                });
             let transform = extracted_sprite.transform.affine()
                 * Affine3A::from_scale_rotation_translation(
                     quad_size.extend(1.0),
@@ -680,22 +1941,81 @@ pub fn prepare_sprite_image_bind_groups(
                     (quad_size * (-extracted_sprite.anchor - Vec2::splat(0.5))).extend(0.0),
                 );
 
+            // Mirrored quads face away from the camera, so with culling enabled they are drawn with
+            // reversed winding. Only then do they need to be split into their own batches.
+            let mirrored =
+                sprite_pipeline.cull_mode.is_some() && transform.matrix3.determinant() < 0.0;
+
+            // Sprites with a different scissor rect, winding, sampler, pipeline, material or
+            // dissolve noise can't be drawn in the same batch
+            let new_batch = batch_image_changed
+                || batch_scissor != extracted_sprite.scissor
+                || batch_mirrored != mirrored
+                || batch_sampler != extracted_sprite.sampler
+                || batch_pipeline != item.pipeline
+                || batch_material != extracted_sprite.material
+                || batch_dissolve_noise != dissolve_noise
+                || extracted_sprite.batch_break;
+
             // Store the vertex data and add the item to the render phase
             sprite_meta
                 .sprite_instance_buffer
                 .push(SpriteInstance::from(
                     &transform,
-                    &extracted_sprite.color,
-                    &uv_offset_scale,
+                    &SpriteInstanceParams {
+                        color: extracted_sprite.color,
+                        uv_offset_scale,
+                        tiling,
+                        border_width,
+                        border_color,
+                        posterize_levels: extracted_sprite.posterize_levels,
+                        color_key,
+                        alpha_cutoff: extracted_sprite.alpha_mode.cutoff(),
+                        hsv_shift,
+                        desaturate: extracted_sprite.desaturate,
+                        dissolve,
+                        dissolve_edge_color,
+                        flags: match array_region {
+                            Some((layer, _)) => {
+                                SpriteInstanceFlags::from_extracted(extracted_sprite)
+                                    .with_texture_array_layer(layer)
+                            }
+                            None => SpriteInstanceFlags::from_extracted(extracted_sprite),
+                        },
+                    },
                 ));
 
-            if batch_image_changed {
+            if new_batch {
                 batch_item_index = item_index;
+                batch_scissor = extracted_sprite.scissor;
+                batch_mirrored = mirrored;
+                batch_sampler = extracted_sprite.sampler;
+                batch_pipeline = item.pipeline;
+                batch_material = extracted_sprite.material;
+                batch_dissolve_noise = dissolve_noise;
+
+                if let (Some(sampler), Some(gpu_image)) =
+                    (batch_sampler, gpu_images.get(batch_image_handle))
+                {
+                    image_bind_groups.prepare_sampled_image(
+                        batch_image_handle,
+                        sampler,
+                        gpu_image,
+                        &samplers,
+                        &render_device,
+                        &sprite_pipeline,
+                    );
+                }
 
                 batches.push((
                     item.entity,
                     SpriteBatch {
                         image_handle_id: batch_image_handle,
+                        scissor: batch_scissor,
+                        mirrored,
+                        sampler: extracted_sprite.sampler,
+                        dissolve_noise,
+                        texture_array: batch_texture_array,
                         range: index..index,
                     },
                 ));
@@ -708,11 +2028,28 @@ pub fn prepare_sprite_image_bind_groups(
             index += 1;
         }
     }
+
+    // Release the textures of removed images once no sprite is drawn with them anymore
+    image_bind_groups
+        .retained
+        .retain(|_, retained| std::mem::take(&mut retained.used));
+
+    *previous_len = batches.len();
+    commands.insert_or_spawn_batch(batches);
+}
+
+/// Uploads the sprite instances and indices of the frame, after [`prepare_sprite_image_bind_groups`]
+/// and the systems adding custom instances with [`SpriteMeta::push_instance`].
+pub fn write_sprite_buffers(
+    render_device: Res<RenderDevice>,
+    render_queue: Res<RenderQueue>,
+    mut sprite_meta: ResMut<SpriteMeta>,
+) {
     sprite_meta
         .sprite_instance_buffer
         .write_buffer(&render_device, &render_queue);
 
-    if sprite_meta.sprite_index_buffer.len() != 6 {
+    if sprite_meta.sprite_index_buffer.len() != 12 {
         sprite_meta.sprite_index_buffer.clear();
 
         // NOTE: This code is creating 6 indices pointing to 4 vertices.
@@ -730,21 +2067,34 @@ pub fn prepare_sprite_image_bind_groups(
         sprite_meta.sprite_index_buffer.push(1);
         sprite_meta.sprite_index_buffer.push(3);
         sprite_meta.sprite_index_buffer.push(2);
+        // The same quad with reversed winding, for mirrored sprites
+        sprite_meta.sprite_index_buffer.push(2);
+        sprite_meta.sprite_index_buffer.push(1);
+        sprite_meta.sprite_index_buffer.push(0);
+        sprite_meta.sprite_index_buffer.push(1);
+        sprite_meta.sprite_index_buffer.push(2);
+        sprite_meta.sprite_index_buffer.push(3);
 
         sprite_meta
             .sprite_index_buffer
             .write_buffer(&render_device, &render_queue);
     }
-
-    *previous_len = batches.len();
-    commands.insert_or_spawn_batch(batches);
 }
 
 /// [`RenderCommand`] for sprite rendering.
+///
+/// Other crates can draw their own [`Transparent2d`] items with it, reusing the sprite pipeline,
+/// instance buffer and image bind groups:
+/// - In [`RenderSet::Queue`](bevy_render::RenderSet::Queue), add an item with this draw function,
+///   a pipeline specialized from [`SpriteExPipeline`] and an entity spawned for it.
+/// - After [`prepare_sprite_image_bind_groups`] and before [`write_sprite_buffers`], add the
+///   item's instances with [`SpriteMeta::push_instance`], call [`ImageBindGroups::prepare_image`]
+///   for its image and insert a [`SpriteBatch`] covering the instances on the item's entity.
 pub type DrawSprite = (
     SetItemPipeline,
     SetSpriteViewBindGroup<0>,
     SetSpriteTextureBindGroup<1>,
+    SetSpriteDissolveBindGroup<2>,
     DrawSpriteBatch,
 );
 
@@ -752,17 +2102,25 @@ pub struct SetSpriteViewBindGroup<const I: usize>;
 
 impl<P: PhaseItem, const I: usize> RenderCommand<P> for SetSpriteViewBindGroup<I> {
     type Param = ();
-    type ViewQuery = (Read<ViewUniformOffset>, Read<SpriteViewBindGroup>);
+    type ViewQuery = (
+        Read<ViewUniformOffset>,
+        Read<SpriteExposureUniformOffset>,
+        Read<SpriteViewBindGroup>,
+    );
     type ItemQuery = ();
 
     fn render<'w>(
         _item: &P,
-        (view_uniform, sprite_view_bind_group): ROQueryItem<'w, Self::ViewQuery>,
+        (view_uniform, exposure_uniform, sprite_view_bind_group): ROQueryItem<'w, Self::ViewQuery>,
         _entity: Option<()>,
         _param: SystemParamItem<'w, '_, Self::Param>,
         pass: &mut TrackedRenderPass<'w>,
     ) -> RenderCommandResult {
-        pass.set_bind_group(I, &sprite_view_bind_group.value, &[view_uniform.offset]);
+        pass.set_bind_group(
+            I,
+            &sprite_view_bind_group.value,
+            &[view_uniform.offset, exposure_uniform.offset],
+        );
         RenderCommandResult::Success
     }
 }
@@ -770,6 +2128,42 @@ impl<P: PhaseItem, const I: usize> RenderCommand<P> for SetSpriteViewBindGroup<I
 pub struct SetSpriteTextureBindGroup<const I: usize>;
 
 impl<P: PhaseItem, const I: usize> RenderCommand<P> for SetSpriteTextureBindGroup<I> {
+    type Param = (SRes<ImageBindGroups>, Option<SRes<SpriteTextureArray>>);
+    type ViewQuery = ();
+    type ItemQuery = Read<SpriteBatch>;
+
+    fn render<'w>(
+        _item: &P,
+        _view: (),
+        batch: Option<&'_ SpriteBatch>,
+        (image_bind_groups, texture_array): SystemParamItem<'w, '_, Self::Param>,
+        pass: &mut TrackedRenderPass<'w>,
+    ) -> RenderCommandResult {
+        let image_bind_groups = image_bind_groups.into_inner();
+        let Some(batch) = batch else {
+            return RenderCommandResult::Failure;
+        };
+        let bind_group = match texture_array {
+            Some(texture_array) if batch.texture_array => {
+                Some(texture_array.into_inner().bind_group())
+            }
+            _ => image_bind_groups
+                .get_batch(batch)
+                .or(image_bind_groups.placeholder.as_ref()),
+        };
+        let Some(bind_group) = bind_group else {
+            return RenderCommandResult::Failure;
+        };
+
+        pass.set_bind_group(I, bind_group, &[]);
+        RenderCommandResult::Success
+    }
+}
+
+/// Binds the noise image of batches with a [`Dissolve`].
+pub struct SetSpriteDissolveBindGroup<const I: usize>;
+
+impl<P: PhaseItem, const I: usize> RenderCommand<P> for SetSpriteDissolveBindGroup<I> {
     type Param = SRes<ImageBindGroups>;
     type ViewQuery = ();
     type ItemQuery = Read<SpriteBatch>;
@@ -781,19 +2175,17 @@ impl<P: PhaseItem, const I: usize> RenderCommand<P> for SetSpriteTextureBindGrou
         image_bind_groups: SystemParamItem<'w, '_, Self::Param>,
         pass: &mut TrackedRenderPass<'w>,
     ) -> RenderCommandResult {
-        let image_bind_groups = image_bind_groups.into_inner();
         let Some(batch) = batch else {
             return RenderCommandResult::Failure;
         };
+        let Some(noise) = batch.dissolve_noise else {
+            return RenderCommandResult::Success;
+        };
+        let Some(bind_group) = image_bind_groups.into_inner().get(noise) else {
+            return RenderCommandResult::Failure;
+        };
 
-        pass.set_bind_group(
-            I,
-            image_bind_groups
-                .values
-                .get(&batch.image_handle_id)
-                .unwrap(),
-            &[],
-        );
+        pass.set_bind_group(I, bind_group, &[]);
         RenderCommandResult::Success
     }
 }
@@ -802,12 +2194,12 @@ pub struct DrawSpriteBatch;
 
 impl<P: PhaseItem> RenderCommand<P> for DrawSpriteBatch {
     type Param = SRes<SpriteMeta>;
-    type ViewQuery = ();
+    type ViewQuery = (Read<ExtractedView>, Option<Read<ViewTarget>>);
     type ItemQuery = Read<SpriteBatch>;
 
     fn render<'w>(
         _item: &P,
-        _view: (),
+        (view, view_target): (&'w ExtractedView, Option<&'w ViewTarget>),
         batch: Option<&'_ SpriteBatch>,
         sprite_meta: SystemParamItem<'w, '_, Self::Param>,
         pass: &mut TrackedRenderPass<'w>,
@@ -830,7 +2222,222 @@ impl<P: PhaseItem> RenderCommand<P> for DrawSpriteBatch {
                 .unwrap()
                 .slice(..),
         );
-        pass.draw_indexed(0..6, 0, batch.range.clone());
+        let indices = if batch.mirrored { 6..12 } else { 0..6 };
+
+        let Some(scissor) = batch.scissor else {
+            pass.draw_indexed(indices, 0, batch.range.clone());
+            return RenderCommandResult::Success;
+        };
+
+        // The scissor rect is relative to the viewport and has to stay inside the render target,
+        // its corners may be given in any order
+        let scissor = URect::from_corners(scissor.min, scissor.max);
+        let viewport_origin = view.viewport.xy();
+        let viewport_size = view.viewport.zw();
+        let target_size = view_target.map_or(UVec2::MAX, |target| {
+            let size = target.main_texture().size();
+            UVec2::new(size.width, size.height)
+        });
+        let min = (scissor.min.min(viewport_size) + viewport_origin).min(target_size);
+        let max = (scissor.max.min(viewport_size) + viewport_origin).min(target_size);
+        let size = max - min;
+        pass.set_scissor_rect(min.x, min.y, size.x, size.y);
+        pass.draw_indexed(indices, 0, batch.range.clone());
+        // Restore the scissor so it doesn't clip the phase items drawn after this batch
+        pass.set_scissor_rect(
+            viewport_origin.x,
+            viewport_origin.y,
+            viewport_size.x,
+            viewport_size.y,
+        );
         RenderCommandResult::Success
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use bevy_ecs::world::CommandQueue;
+
+    use super::*;
+
+    #[test]
+    fn blend_state_round_trips_through_key() {
+        assert_eq!(
+            SpritePipelineKey::NONE.blend_state(),
+            BlendState::ALPHA_BLENDING
+        );
+        let mut blend_states = vec![
+            BlendState::ALPHA_BLENDING,
+            BlendState::PREMULTIPLIED_ALPHA_BLENDING,
+            BlendState::REPLACE,
+        ];
+        // Every factor and operation, in both components
+        for (index, factor) in BLEND_FACTORS.into_iter().enumerate() {
+            let operation = BLEND_OPERATIONS[index % BLEND_OPERATIONS.len()];
+            let other = BLEND_FACTORS[BLEND_FACTORS.len() - 1 - index];
+            blend_states.push(BlendState {
+                color: BlendComponent {
+                    src_factor: factor,
+                    dst_factor: other,
+                    operation,
+                },
+                alpha: BlendComponent {
+                    src_factor: other,
+                    dst_factor: factor,
+                    operation: BLEND_OPERATIONS[(index + 2) % BLEND_OPERATIONS.len()],
+                },
+            });
+        }
+        for blend_state in blend_states {
+            let key = SpritePipelineKey::from_blend_state(blend_state);
+            assert!(key.contains(SpritePipelineKey::CUSTOM_BLEND));
+            assert_eq!(key.blend_state(), blend_state);
+
+            // The blend state bits don't overlap the other bits of the key
+            let key = key
+                | SpritePipelineKey::from_msaa_samples(4)
+                | SpritePipelineKey::TONEMAP_METHOD_BLENDER_FILMIC
+                | SpritePipelineKey::TEXTURE_ARRAY;
+            assert_eq!(key.blend_state(), blend_state);
+            assert_eq!(key.msaa_samples(), 4);
+        }
+    }
+
+    #[test]
+    fn dual_source_blend_state_falls_back_to_alpha_blending() {
+        let mut blend_state = BlendState::ALPHA_BLENDING;
+        blend_state.alpha.dst_factor = BlendFactor::OneMinusSrc1Alpha;
+        let key = SpritePipelineKey::from_blend_state(blend_state);
+        assert!(!key.contains(SpritePipelineKey::CUSTOM_BLEND));
+        assert_eq!(key.blend_state(), BlendState::ALPHA_BLENDING);
+    }
+
+    fn sprite(translation: Vec3) -> ExtractedSprite {
+        ExtractedSprite {
+            transform: GlobalTransform::from_translation(translation),
+            color: LinearRgba::WHITE,
+            rect: None,
+            custom_size: None,
+            image_handle_id: AssetId::default(),
+            flip_x: false,
+            flip_y: false,
+            anchor: Vec2::ZERO,
+            repeat: BVec2::FALSE,
+            sampling: SpriteSampling::Texture,
+            scissor: None,
+            border: None,
+            posterize_levels: 0,
+            hsv_shift: None,
+            desaturate: 0.0,
+            dissolve: None,
+            color_key: None,
+            alpha_mode: SpriteAlphaMode::default(),
+            blend_state: None,
+            fragment_shader: None,
+            sampler: None,
+            material: None,
+            batch_break: false,
+            sort_bias: 0.0,
+            original_entity: None,
+        }
+    }
+
+    /// Extracted sprites with an original sprite at `original`, and a sprite derived from it and
+    /// one derived from that derived sprite, both at `derived`.
+    fn derived_sprites(
+        world: &mut World,
+        original: Vec3,
+        derived: Vec3,
+    ) -> (ExtractedSprites, Entity, Entity, Entity) {
+        let mut extracted_sprites = ExtractedSprites::default();
+        let original_entity = world.spawn_empty().id();
+        extracted_sprites
+            .sprites
+            .insert(original_entity, sprite(original));
+        let mut queue = CommandQueue::default();
+        let mut commands = Commands::new(&mut queue, world);
+        let derived_entity =
+            extracted_sprites.insert_derived(&mut commands, original_entity, sprite(derived));
+        let nested_entity =
+            extracted_sprites.insert_derived(&mut commands, derived_entity, sprite(derived));
+        queue.apply(world);
+        (
+            extracted_sprites,
+            original_entity,
+            derived_entity,
+            nested_entity,
+        )
+    }
+
+    #[test]
+    fn derived_sprites_resolve_original_entity() {
+        let mut world = World::new();
+        let (extracted_sprites, original, derived, nested) =
+            derived_sprites(&mut world, Vec3::ZERO, Vec3::ZERO);
+        assert_eq!(extracted_sprites.sprites[&original].original_entity, None);
+        assert_eq!(
+            extracted_sprites.sprites[&derived].original_entity,
+            Some(original)
+        );
+        assert_eq!(
+            extracted_sprites.sprites[&nested].original_entity,
+            Some(original)
+        );
+        assert!(world.get_entity(derived).is_some());
+    }
+
+    #[test]
+    fn derived_sprites_sort_like_original() {
+        let mut world = World::new();
+        let (mut extracted_sprites, original, derived, _) = derived_sprites(
+            &mut world,
+            Vec3::new(0.0, 10.0, 5.0),
+            Vec3::new(0.0, -10.0, -3.0),
+        );
+        for sort_mode in [
+            SpriteSortMode::Z,
+            SpriteSortMode::Y,
+            SpriteSortMode::ZLayersThenY,
+        ] {
+            let key = |entity| {
+                extracted_sprites.sort_key(&extracted_sprites.sprites[&entity], 0.5, sort_mode)
+            };
+            assert_eq!(key(derived), key(original));
+        }
+
+        extracted_sprites.sprites[&derived].sort_bias = -1.0;
+        let key = |entity| {
+            extracted_sprites.sort_key(&extracted_sprites.sprites[&entity], 0.0, SpriteSortMode::Z)
+        };
+        assert_eq!(key(derived), FloatOrd(4.0));
+        assert_eq!(key(original), FloatOrd(5.0));
+    }
+
+    #[test]
+    fn derived_sprites_are_visible_with_original() {
+        let mut world = World::new();
+        let (mut extracted_sprites, original, derived, nested) =
+            derived_sprites(&mut world, Vec3::ZERO, Vec3::ZERO);
+        let hidden = world.spawn_empty().id();
+        extracted_sprites.sprites.insert(hidden, sprite(Vec3::ZERO));
+
+        let visible = |visible_entities: &VisibleEntities| {
+            let mut view_entities = FixedBitSet::new();
+            extend_view_entities::<WithSprite>(&mut view_entities, visible_entities);
+            extracted_sprites
+                .iter_visible(&view_entities)
+                .map(|(entity, _)| *entity)
+                .collect::<Vec<_>>()
+        };
+
+        let mut visible_entities = VisibleEntities::default();
+        visible_entities.push::<WithSprite>(original);
+        assert_eq!(visible(&visible_entities), [original, derived, nested]);
+
+        // Only the original entity decides, not the render world entity of a derived sprite
+        let mut visible_entities = VisibleEntities::default();
+        visible_entities.push::<WithSprite>(derived);
+        visible_entities.push::<crate::WithMesh2d>(original);
+        assert_eq!(visible(&visible_entities), []);
+    }
+}
@@ -1,14 +1,13 @@
-#ifdef TONEMAP_IN_SHADER
-#import bevy_core_pipeline::tonemapping
-#endif
-
-#import bevy_render::{
-    maths::affine3_to_square,
-    view::View,
+#import bevy_sprite_ex::{
+    sprite_border::apply_border,
+    sprite_effects::{desaturate, dissolve, hsv_shift, posterize},
+    sprite_sampling::{apply_color_key, sample_sprite},
+    sprite_tonemapping::tonemap,
+    sprite_types::{SpriteInstance, VertexOutput},
+    sprite_vertex::sprite_vertex,
+    sprite_view_bindings::exposure,
 }
 
-#import bevy_sprite_ex::sprite_view_bindings::view
-
 struct VertexInput {
     @builtin(vertex_index) index: u32,
     // NOTE: Instance-rate vertex buffer members prefixed with i_
@@ -19,45 +18,50 @@ struct VertexInput {
     @location(2) i_model_transpose_col2: vec4<f32>,
     @location(3) i_color: vec4<f32>,
     @location(4) i_uv_offset_scale: vec4<f32>,
+    @location(5) i_tiling: vec2<f32>,
+    @location(6) i_border_width: vec2<f32>,
+    @location(7) i_border_color: vec4<f32>,
+    @location(8) i_flags: u32,
+    @location(9) i_posterize_levels: f32,
+    @location(10) i_color_key: vec4<f32>,
+    @location(11) i_alpha_cutoff: f32,
+    @location(12) i_hsv_shift: vec3<f32>,
+    @location(13) i_desaturate: f32,
+    @location(14) i_dissolve: vec2<f32>,
+    @location(15) i_dissolve_edge_color: vec4<f32>,
 }
 
-struct VertexOutput {
-    @builtin(position) clip_position: vec4<f32>,
-    @location(0) uv: vec2<f32>,
-    @location(1) @interpolate(flat) color: vec4<f32>,
-};
-
 @vertex
 fn vertex(in: VertexInput) -> VertexOutput {
-    var out: VertexOutput;
-
-    let vertex_position = vec3<f32>(
-        f32(in.index & 0x1u),
-        f32((in.index & 0x2u) >> 1u),
-        0.0
-    );
-
-    out.clip_position = view.clip_from_world * affine3_to_square(mat3x4<f32>(
-        in.i_model_transpose_col0,
-        in.i_model_transpose_col1,
-        in.i_model_transpose_col2,
-    )) * vec4<f32>(vertex_position, 1.0);
-    out.uv = vec2<f32>(vertex_position.xy) * in.i_uv_offset_scale.zw + in.i_uv_offset_scale.xy;
-    out.color = in.i_color;
-
-    return out;
+    return sprite_vertex(in.index, SpriteInstance(
+        mat3x4<f32>(
+            in.i_model_transpose_col0,
+            in.i_model_transpose_col1,
+            in.i_model_transpose_col2,
+        ),
+        in.i_color,
+        in.i_uv_offset_scale,
+        in.i_tiling,
+        in.i_border_width,
+        in.i_border_color,
+        in.i_flags,
+        in.i_posterize_levels,
+        in.i_color_key,
+        in.i_alpha_cutoff,
+        in.i_hsv_shift,
+        in.i_desaturate,
+        in.i_dissolve,
+        in.i_dissolve_edge_color,
+    ));
 }
 
-@group(1) @binding(0) var sprite_texture: texture_2d<f32>;
-@group(1) @binding(1) var sprite_sampler: sampler;
-
 @fragment
 fn fragment(in: VertexOutput) -> @location(0) vec4<f32> {
-    var color = in.color * textureSample(sprite_texture, sprite_sampler, in.uv);
-
-#ifdef TONEMAP_IN_SHADER
-    color = tonemapping::tone_mapping(color, view.color_grading);
-#endif
-
-    return color;
+    let tinted = in.color * apply_color_key(sample_sprite(in), in);
+    let effects = desaturate(apply_border(posterize(hsv_shift(tinted, in), in), in), in);
+    let color = dissolve(effects, in);
+    if color.a < in.alpha_cutoff {
+        discard;
+    }
+    return tonemap(vec4(color.rgb * exposure, color.a));
 }
@@ -0,0 +1,26 @@
+#define_import_path bevy_sprite_ex::sprite_border
+
+#import bevy_sprite_ex::sprite_types::VertexOutput
+
+// Draws the border of the fragment `in` over `color` when the SPRITE_BORDER shader def is set,
+// returns `color` unchanged otherwise.
+fn apply_border(color: vec4<f32>, in: VertexOutput) -> vec4<f32> {
+#ifdef SPRITE_BORDER
+    // Distance to the closest edge along each axis, antialiased over one pixel
+    let edge_distance = min(in.quad_position, 1.0 - in.quad_position);
+    let pixel_size = fwidth(in.quad_position);
+    let axis_coverage = saturate((in.border_width - edge_distance) / pixel_size + 0.5);
+    let border_alpha = in.border_color.a * max(axis_coverage.x, axis_coverage.y);
+
+    // Composite the border over the sprite color, both with straight alpha
+    let alpha = border_alpha + color.a * (1.0 - border_alpha);
+    if alpha <= 0.0 {
+        return color;
+    }
+    let rgb = (in.border_color.rgb * border_alpha + color.rgb * color.a * (1.0 - border_alpha))
+        / alpha;
+    return vec4<f32>(rgb, alpha);
+#else
+    return color;
+#endif
+}
@@ -0,0 +1,83 @@
+#define_import_path bevy_sprite_ex::sprite_effects
+
+#import bevy_sprite_ex::sprite_types::VertexOutput
+
+#ifdef SPRITE_DISSOLVE
+@group(2) @binding(0) var dissolve_noise_texture: texture_2d<f32>;
+@group(2) @binding(1) var dissolve_noise_sampler: sampler;
+#endif
+
+// Reduces the color channels of `color` to `in.posterize_levels` levels when the SPRITE_POSTERIZE
+// shader def is set, returns `color` unchanged otherwise.
+fn posterize(color: vec4<f32>, in: VertexOutput) -> vec4<f32> {
+#ifdef SPRITE_POSTERIZE
+    if in.posterize_levels >= 2.0 {
+        let steps = in.posterize_levels - 1.0;
+        return vec4<f32>(round(saturate(color.rgb) * steps) / steps, color.a);
+    }
+#endif
+    return color;
+}
+
+// Rotates the hue and scales the saturation and value of `color` by `in.hsv_shift` when the
+// SPRITE_HSV_SHIFT shader def is set, returns `color` unchanged otherwise.
+fn hsv_shift(color: vec4<f32>, in: VertexOutput) -> vec4<f32> {
+#ifdef SPRITE_HSV_SHIFT
+    if any(in.hsv_shift != vec3<f32>(0.0, 1.0, 1.0)) {
+        let hsv = rgb_to_hsv(max(color.rgb, vec3<f32>(0.0)));
+        let shifted = vec3<f32>(
+            fract(hsv.x + in.hsv_shift.x),
+            saturate(hsv.y * in.hsv_shift.y),
+            hsv.z * in.hsv_shift.z,
+        );
+        return vec4<f32>(hsv_to_rgb(shifted), color.a);
+    }
+#endif
+    return color;
+}
+
+// Blends `color` towards its luminance by `in.desaturate`.
+fn desaturate(color: vec4<f32>, in: VertexOutput) -> vec4<f32> {
+    let luminance = dot(color.rgb, vec3<f32>(0.2126, 0.7152, 0.0722));
+    return vec4<f32>(mix(color.rgb, vec3<f32>(luminance), in.desaturate), color.a);
+}
+
+// Discards the fragments of `color` where the dissolve noise is below `in.dissolve.x` and tints
+// those within `in.dissolve.y` above it when the SPRITE_DISSOLVE shader def is set, returns `color`
+// unchanged otherwise.
+fn dissolve(color: vec4<f32>, in: VertexOutput) -> vec4<f32> {
+#ifdef SPRITE_DISSOLVE
+    let noise = textureSample(dissolve_noise_texture, dissolve_noise_sampler, in.quad_position).r;
+    let progress = in.dissolve.x;
+    if noise < progress {
+        discard;
+    }
+    if progress > 0.0 && noise < progress + in.dissolve.y {
+        return vec4<f32>(in.dissolve_edge_color.rgb, in.dissolve_edge_color.a * color.a);
+    }
+#endif
+    return color;
+}
+
+// Hue in turns, saturation and value of `rgb`
+fn rgb_to_hsv(rgb: vec3<f32>) -> vec3<f32> {
+    let value = max(rgb.r, max(rgb.g, rgb.b));
+    let chroma = value - min(rgb.r, min(rgb.g, rgb.b));
+    var hue = 0.0;
+    if chroma > 0.0 {
+        if value == rgb.r {
+            hue = (rgb.g - rgb.b) / chroma;
+        } else if value == rgb.g {
+            hue = (rgb.b - rgb.r) / chroma + 2.0;
+        } else {
+            hue = (rgb.r - rgb.g) / chroma + 4.0;
+        }
+    }
+    let saturation = select(0.0, chroma / value, value > 0.0);
+    return vec3<f32>(fract(hue / 6.0), saturation, value);
+}
+
+fn hsv_to_rgb(hsv: vec3<f32>) -> vec3<f32> {
+    let k = fract(hsv.x + vec3<f32>(1.0, 2.0 / 3.0, 1.0 / 3.0)) * 6.0 - 3.0;
+    return hsv.z * mix(vec3<f32>(1.0), saturate(abs(k) - 1.0), hsv.y);
+}
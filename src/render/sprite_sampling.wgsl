@@ -0,0 +1,105 @@
+#define_import_path bevy_sprite_ex::sprite_sampling
+
+#import bevy_sprite_ex::sprite_types::{
+    VertexOutput,
+    SPRITE_FLAGS_COLOR_KEY_BIT,
+    SPRITE_FLAGS_ROTSPRITE_BIT,
+    SPRITE_FLAGS_TEXTURE_ARRAY_LAYER_SHIFT_BITS,
+}
+
+// Shader defs:
+// - SPRITE_TILING: wrap repeating sprites back into their texture region.
+// - SPRITE_ROTSPRITE: sample sprites flagged with `SPRITE_FLAGS_ROTSPRITE_BIT` with
+//   `sample_rotsprite`.
+// - SPRITE_TEXTURE_ARRAY: sample the layer of `SpriteTextureArray` in the instance flags. Excludes
+//   SPRITE_ROTSPRITE.
+
+#ifdef SPRITE_TEXTURE_ARRAY
+@group(1) @binding(0) var sprite_texture: texture_2d_array<f32>;
+#else
+@group(1) @binding(0) var sprite_texture: texture_2d<f32>;
+#endif
+@group(1) @binding(1) var sprite_sampler: sampler;
+
+#ifndef SPRITE_TEXTURE_ARRAY
+
+fn load_texel(texel: vec2<i32>, bounds: vec4<i32>) -> vec4<f32> {
+    return textureLoad(sprite_texture, clamp(texel, bounds.xy, bounds.zw), 0);
+}
+
+fn texels_equal(a: vec4<f32>, b: vec4<f32>) -> bool {
+    return all(a == b);
+}
+
+// A Scale2x (EPX) edge heuristic standing in for RotSprite: the texel under `uv` is split into
+// quadrants which take the color of a neighbor when the neighbors form an edge through it. It
+// doesn't resample the rotation itself. Neighbors are only read from the texture region
+// `uv_offset_scale`, so the texels around a sprite's rect don't bleed into its edges.
+fn sample_rotsprite(uv: vec2<f32>, uv_offset_scale: vec4<f32>) -> vec4<f32> {
+    let size = vec2<i32>(textureDimensions(sprite_texture));
+    let region_min = min(uv_offset_scale.xy, uv_offset_scale.xy + uv_offset_scale.zw);
+    let region_max = max(uv_offset_scale.xy, uv_offset_scale.xy + uv_offset_scale.zw);
+    let min_texel = clamp(vec2<i32>(round(region_min * vec2<f32>(size))), vec2<i32>(0), size - 1);
+    let max_texel = clamp(vec2<i32>(round(region_max * vec2<f32>(size))) - 1, min_texel, size - 1);
+    let bounds = vec4<i32>(min_texel, max_texel);
+
+    let position = uv * vec2<f32>(size);
+    let texel = vec2<i32>(floor(position));
+    let quadrant = fract(position) >= vec2<f32>(0.5);
+
+    let p = load_texel(texel, bounds);
+    let above = load_texel(texel + vec2<i32>(0, -1), bounds);
+    let right = load_texel(texel + vec2<i32>(1, 0), bounds);
+    let left = load_texel(texel + vec2<i32>(-1, 0), bounds);
+    let below = load_texel(texel + vec2<i32>(0, 1), bounds);
+
+    // Pick the two neighbors adjacent to this quadrant and the two opposite it
+    let horizontal = select(left, right, quadrant.x);
+    let vertical = select(above, below, quadrant.y);
+    let opposite_horizontal = select(right, left, quadrant.x);
+    let opposite_vertical = select(below, above, quadrant.y);
+
+    if texels_equal(horizontal, vertical)
+        && !texels_equal(horizontal, opposite_vertical)
+        && !texels_equal(vertical, opposite_horizontal) {
+        return vertical;
+    }
+    return p;
+}
+#endif
+
+// Samples the sprite texture at the fragment `in`.
+fn sample_sprite(in: VertexOutput) -> vec4<f32> {
+#ifdef SPRITE_TILING
+    // Wrap repeating axes back into the texture region. The gradients of the unwrapped UVs are
+    // used so the wrap doesn't cause a mip level discontinuity at tile seams.
+    let tile_uv = select(in.tile_uv, fract(in.tile_uv), in.tiling != vec2<f32>(1.0));
+    let uv = tile_uv * in.uv_offset_scale.zw + in.uv_offset_scale.xy;
+#else
+    let uv = in.uv;
+#endif
+    let uv_dx = dpdx(in.uv);
+    let uv_dy = dpdy(in.uv);
+
+#ifdef SPRITE_ROTSPRITE
+    if (in.flags & SPRITE_FLAGS_ROTSPRITE_BIT) != 0u {
+        return sample_rotsprite(uv, in.uv_offset_scale);
+    }
+#endif
+#ifdef SPRITE_TEXTURE_ARRAY
+    let layer = in.flags >> SPRITE_FLAGS_TEXTURE_ARRAY_LAYER_SHIFT_BITS;
+    return textureSampleGrad(sprite_texture, sprite_sampler, uv, layer, uv_dx, uv_dy);
+#else
+    return textureSampleGrad(sprite_texture, sprite_sampler, uv, uv_dx, uv_dy);
+#endif
+}
+
+// Makes `texel` transparent if the fragment `in` is flagged with `SPRITE_FLAGS_COLOR_KEY_BIT` and
+// the texel's color lies within the key's tolerance.
+fn apply_color_key(texel: vec4<f32>, in: VertexOutput) -> vec4<f32> {
+    if (in.flags & SPRITE_FLAGS_COLOR_KEY_BIT) != 0u
+        && distance(texel.rgb, in.color_key.rgb) <= in.color_key.w {
+        return vec4<f32>(0.0);
+    }
+    return texel;
+}
@@ -0,0 +1,16 @@
+#define_import_path bevy_sprite_ex::sprite_tonemapping
+
+#ifdef TONEMAP_IN_SHADER
+#import bevy_core_pipeline::tonemapping
+#import bevy_sprite_ex::sprite_view_bindings::view
+#endif
+
+// Applies the view's tonemapping when the TONEMAP_IN_SHADER shader def is set, returns `color`
+// unchanged otherwise.
+fn tonemap(color: vec4<f32>) -> vec4<f32> {
+#ifdef TONEMAP_IN_SHADER
+    return tonemapping::tone_mapping(color, view.color_grading);
+#else
+    return color;
+#endif
+}
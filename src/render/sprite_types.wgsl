@@ -0,0 +1,55 @@
+#define_import_path bevy_sprite_ex::sprite_types
+
+// Mirrors `SpriteInstanceFlags`
+const SPRITE_FLAGS_ROTSPRITE_BIT: u32 = 1u;
+const SPRITE_FLAGS_COLOR_KEY_BIT: u32 = 2u;
+// The layer of `SpriteTextureArray` is in the bits above
+const SPRITE_FLAGS_TEXTURE_ARRAY_LAYER_SHIFT_BITS: u32 = 16u;
+
+// The data of one instanced sprite, see `SpriteInstance`
+struct SpriteInstance {
+    // Transpose of the affine 4x3 model matrix
+    model_transpose: mat3x4<f32>,
+    color: vec4<f32>,
+    uv_offset_scale: vec4<f32>,
+    tiling: vec2<f32>,
+    // Border thickness as a fraction of the quad size
+    border_width: vec2<f32>,
+    border_color: vec4<f32>,
+    flags: u32,
+    // Levels per color channel, less than 2 if the sprite isn't posterized
+    posterize_levels: f32,
+    // Key color in xyz and tolerance in w, for sprites flagged with `SPRITE_FLAGS_COLOR_KEY_BIT`
+    color_key: vec4<f32>,
+    // Fragments with an alpha below it are discarded
+    alpha_cutoff: f32,
+    // Hue rotation in turns, saturation factor and value factor
+    hsv_shift: vec3<f32>,
+    // Blend factor towards grayscale
+    desaturate: f32,
+    // Dissolve progress and edge width, zero if the sprite doesn't dissolve
+    dissolve: vec2<f32>,
+    dissolve_edge_color: vec4<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+    @location(1) @interpolate(flat) color: vec4<f32>,
+    // Position within the quad in units of texture repeats
+    @location(2) tile_uv: vec2<f32>,
+    @location(3) @interpolate(flat) uv_offset_scale: vec4<f32>,
+    @location(4) @interpolate(flat) tiling: vec2<f32>,
+    @location(5) @interpolate(flat) flags: u32,
+    // Position within the quad, from (0, 0) to (1, 1)
+    @location(6) quad_position: vec2<f32>,
+    @location(7) @interpolate(flat) border_width: vec2<f32>,
+    @location(8) @interpolate(flat) border_color: vec4<f32>,
+    @location(9) @interpolate(flat) posterize_levels: f32,
+    @location(10) @interpolate(flat) color_key: vec4<f32>,
+    @location(11) @interpolate(flat) alpha_cutoff: f32,
+    @location(12) @interpolate(flat) hsv_shift: vec3<f32>,
+    @location(13) @interpolate(flat) desaturate: f32,
+    @location(14) @interpolate(flat) dissolve: vec2<f32>,
+    @location(15) @interpolate(flat) dissolve_edge_color: vec4<f32>,
+};
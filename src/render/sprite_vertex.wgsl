@@ -0,0 +1,39 @@
+#define_import_path bevy_sprite_ex::sprite_vertex
+
+#import bevy_render::maths::affine3_to_square
+#import bevy_sprite_ex::{
+    sprite_types::{SpriteInstance, VertexOutput},
+    sprite_view_bindings::view,
+}
+
+// Expands `instance` into the corner `vertex_index` of its quad.
+fn sprite_vertex(vertex_index: u32, instance: SpriteInstance) -> VertexOutput {
+    var out: VertexOutput;
+
+    let vertex_position = vec3<f32>(
+        f32(vertex_index & 0x1u),
+        f32((vertex_index & 0x2u) >> 1u),
+        0.0
+    );
+
+    out.clip_position = view.clip_from_world * affine3_to_square(instance.model_transpose)
+        * vec4<f32>(vertex_position, 1.0);
+    out.tile_uv = vertex_position.xy * instance.tiling;
+    out.uv = out.tile_uv * instance.uv_offset_scale.zw + instance.uv_offset_scale.xy;
+    out.color = instance.color;
+    out.uv_offset_scale = instance.uv_offset_scale;
+    out.tiling = instance.tiling;
+    out.flags = instance.flags;
+    out.quad_position = vertex_position.xy;
+    out.border_width = instance.border_width;
+    out.border_color = instance.border_color;
+    out.posterize_levels = instance.posterize_levels;
+    out.color_key = instance.color_key;
+    out.alpha_cutoff = instance.alpha_cutoff;
+    out.hsv_shift = instance.hsv_shift;
+    out.desaturate = instance.desaturate;
+    out.dissolve = instance.dissolve;
+    out.dissolve_edge_color = instance.dissolve_edge_color;
+
+    return out;
+}
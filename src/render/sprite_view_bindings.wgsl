@@ -1,9 +1,20 @@
 #define_import_path bevy_sprite_ex::sprite_view_bindings
 
-#import bevy_render::view::View
+#import bevy_render::{
+    globals::Globals,
+    view::View,
+}
 
 @group(0) @binding(0) var<uniform> view: View;
 
+// Only bound when the pipeline tonemaps, see `SpriteExPlugin::disable_tonemapping`
+#ifdef TONEMAP_IN_SHADER
 @group(0) @binding(1) var dt_lut_texture: texture_3d<f32>;
 @group(0) @binding(2) var dt_lut_sampler: sampler;
+#endif
 
+// Time since startup, frame delta time and frame count, for animated shader effects
+@group(0) @binding(3) var<uniform> globals: Globals;
+
+// Multiplier of the color of every sprite, see `SpriteExposure`
+@group(0) @binding(4) var<uniform> exposure: f32;
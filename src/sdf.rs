@@ -0,0 +1,378 @@
+use bevy_asset::{
+    io::Writer,
+    processor::LoadTransformAndSave,
+    saver::{AssetSaver, SavedAsset},
+    transformer::{AssetTransformer, TransformedAsset},
+    AsyncWriteExt,
+};
+use bevy_render::{
+    render_asset::RenderAssetUsages,
+    render_resource::{Extent3d, TextureDimension, TextureFormat},
+    texture::{
+        Image, ImageFormat, ImageFormatSetting, ImageLoader, ImageLoaderSettings,
+        TextureFormatPixelInfo,
+    },
+};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// Asset processor converting an image into a signed distance field.
+///
+/// Select it for an image in its `.meta` file while running with processed assets
+/// (`AssetMode::Processed`). The output is a single channel PNG where `0.5` lies on the shape's
+/// edge, larger values inside and smaller values outside, see [`SdfSettings`].
+pub type SdfImageProcessor = LoadTransformAndSave<ImageLoader, SdfTransformer, SdfImageSaver>;
+
+/// The channel of the source image defining the shape of a signed distance field.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SdfChannel {
+    /// The alpha channel.
+    #[default]
+    Alpha,
+    /// The luminance of the color channels, for grayscale masks without alpha.
+    Luminance,
+}
+
+/// Settings of [`SdfTransformer`].
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+pub struct SdfSettings {
+    /// The channel defining the shape.
+    pub channel: SdfChannel,
+    /// Texels whose channel value is at least this are inside the shape.
+    pub threshold: f32,
+    /// The distance in texels from the edge at which the field saturates to `0.0` outside and
+    /// `1.0` inside the shape.
+    pub spread: f32,
+}
+
+impl Default for SdfSettings {
+    fn default() -> Self {
+        Self {
+            channel: SdfChannel::Alpha,
+            threshold: 0.5,
+            spread: 8.0,
+        }
+    }
+}
+
+#[non_exhaustive]
+#[derive(Debug, Error)]
+pub enum SdfError {
+    #[error("Could not read image with texture format {0:?}")]
+    UnsupportedFormat(TextureFormat),
+    #[error("Could not encode signed distance field: {0}")]
+    Encode(#[from] image::ImageError),
+    #[error("Could not write signed distance field: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// [`AssetTransformer`] turning an [`Image`] into an `R8Unorm` signed distance field of the same
+/// size.
+#[derive(Default, Clone, Copy)]
+pub struct SdfTransformer;
+
+impl AssetTransformer for SdfTransformer {
+    type AssetInput = Image;
+    type AssetOutput = Image;
+    type Settings = SdfSettings;
+    type Error = SdfError;
+
+    async fn transform<'a>(
+        &'a self,
+        asset: TransformedAsset<Self::AssetInput>,
+        settings: &'a Self::Settings,
+    ) -> Result<TransformedAsset<Self::AssetOutput>, Self::Error> {
+        let sdf = generate_sdf(asset.get(), settings)?;
+        Ok(asset.replace_asset(sdf))
+    }
+}
+
+/// [`AssetSaver`] writing single channel [`Image`]s, such as the output of [`SdfTransformer`],
+/// as grayscale PNGs.
+#[derive(Default, Clone, Copy)]
+pub struct SdfImageSaver;
+
+impl AssetSaver for SdfImageSaver {
+    type Asset = Image;
+    type Settings = ();
+    type OutputLoader = ImageLoader;
+    type Error = SdfError;
+
+    async fn save<'a>(
+        &'a self,
+        writer: &'a mut Writer,
+        asset: SavedAsset<'a, Self::Asset>,
+        _settings: &'a Self::Settings,
+    ) -> Result<ImageLoaderSettings, Self::Error> {
+        let format = asset.texture_descriptor.format;
+        if format != TextureFormat::R8Unorm {
+            return Err(SdfError::UnsupportedFormat(format));
+        }
+
+        let mut bytes = Vec::new();
+        image::GrayImage::from_raw(asset.width(), asset.height(), asset.data.clone())
+            .ok_or(SdfError::UnsupportedFormat(format))?
+            .write_to(
+                &mut std::io::Cursor::new(&mut bytes),
+                image::ImageFormat::Png,
+            )?;
+        writer.write_all(&bytes).await?;
+
+        Ok(ImageLoaderSettings {
+            format: ImageFormatSetting::Format(ImageFormat::Png),
+            // Distances are linear data and must not be converted from sRGB
+            is_srgb: false,
+            ..Default::default()
+        })
+    }
+}
+
+/// Generates an `R8Unorm` signed distance field from the shape defined by `settings.channel` of
+/// `image`.
+pub fn generate_sdf(image: &Image, settings: &SdfSettings) -> Result<Image, SdfError> {
+    let format = image.texture_descriptor.format;
+    let (width, height) = (image.width(), image.height());
+    // (luminance, alpha) of a texel
+    let read: fn(&[u8]) -> (f32, u8) = match format {
+        TextureFormat::R8Unorm => |texel| (texel[0] as f32, 255),
+        TextureFormat::Rg8Unorm => |texel| (texel[0] as f32, texel[1]),
+        TextureFormat::Rgba8Unorm | TextureFormat::Rgba8UnormSrgb => {
+            |texel| (luminance(texel[0], texel[1], texel[2]), texel[3])
+        }
+        TextureFormat::Bgra8Unorm | TextureFormat::Bgra8UnormSrgb => {
+            |texel| (luminance(texel[2], texel[1], texel[0]), texel[3])
+        }
+        _ => return Err(SdfError::UnsupportedFormat(format)),
+    };
+    let texel_size = format.pixel_size();
+    if image.data.len() < (width * height) as usize * texel_size {
+        return Err(SdfError::UnsupportedFormat(format));
+    }
+
+    let threshold = settings.threshold.clamp(0.0, 1.0) * 255.0;
+    let inside: Vec<bool> = image
+        .data
+        .chunks_exact(texel_size)
+        .take((width * height) as usize)
+        .map(|texel| {
+            let (luminance, alpha) = read(texel);
+            let value = match settings.channel {
+                SdfChannel::Alpha => alpha as f32,
+                SdfChannel::Luminance => luminance,
+            };
+            value >= threshold && value > 0.0
+        })
+        .collect();
+
+    let distance_to_inside = distance_transform(&inside, width as usize, height as usize, true);
+    let distance_to_outside = distance_transform(&inside, width as usize, height as usize, false);
+
+    let spread = settings.spread.max(f32::EPSILON);
+    let data = inside
+        .iter()
+        .zip(distance_to_inside.iter().zip(&distance_to_outside))
+        .map(|(&inside, (&to_inside, &to_outside))| {
+            // The edge lies halfway between texel centers
+            let signed_distance = if inside {
+                to_outside - 0.5
+            } else {
+                0.5 - to_inside
+            };
+            ((0.5 + 0.5 * signed_distance / spread).clamp(0.0, 1.0) * 255.0).round() as u8
+        })
+        .collect();
+
+    Ok(Image::new(
+        Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::R8Unorm,
+        RenderAssetUsages::default(),
+    ))
+}
+
+fn luminance(red: u8, green: u8, blue: u8) -> f32 {
+    0.2126 * red as f32 + 0.7152 * green as f32 + 0.0722 * blue as f32
+}
+
+/// Euclidean distance from each texel to the nearest texel where `mask == target`, using the
+/// separable algorithm by Felzenszwalb and Huttenlocher.
+fn distance_transform(mask: &[bool], width: usize, height: usize, target: bool) -> Vec<f32> {
+    // Large enough to never be the minimum, but finite to keep the parabola math well defined
+    let far = ((width * width + height * height) as f32).max(1.0) * 4.0;
+    let mut grid: Vec<f32> = mask
+        .iter()
+        .map(|&value| if value == target { 0.0 } else { far })
+        .collect();
+
+    let mut line = vec![0.0; width.max(height)];
+    let mut output = vec![0.0; width.max(height)];
+    let mut vertices = vec![0; width.max(height)];
+    let mut boundaries = vec![0.0; width.max(height) + 1];
+
+    for x in 0..width {
+        for y in 0..height {
+            line[y] = grid[y * width + x];
+        }
+        squared_distance_1d(
+            &line[..height],
+            &mut output[..height],
+            &mut vertices,
+            &mut boundaries,
+        );
+        for y in 0..height {
+            grid[y * width + x] = output[y];
+        }
+    }
+    for y in 0..height {
+        let row = &mut grid[y * width..(y + 1) * width];
+        line[..width].copy_from_slice(row);
+        squared_distance_1d(
+            &line[..width],
+            &mut output[..width],
+            &mut vertices,
+            &mut boundaries,
+        );
+        row.copy_from_slice(&output[..width]);
+    }
+
+    grid.into_iter().map(f32::sqrt).collect()
+}
+
+/// One dimensional squared distance transform of the sampled function `f` into `output`.
+fn squared_distance_1d(
+    f: &[f32],
+    output: &mut [f32],
+    vertices: &mut [usize],
+    boundaries: &mut [f32],
+) {
+    if f.is_empty() {
+        return;
+    }
+
+    // Lower envelope of the parabolas rooted at each sample
+    let intersection = |q: usize, p: usize| {
+        ((f[q] + (q * q) as f32) - (f[p] + (p * p) as f32)) / (2.0 * q as f32 - 2.0 * p as f32)
+    };
+    let mut k = 0;
+    vertices[0] = 0;
+    boundaries[0] = f32::NEG_INFINITY;
+    boundaries[1] = f32::INFINITY;
+    for q in 1..f.len() {
+        let mut s = intersection(q, vertices[k]);
+        while s <= boundaries[k] {
+            k -= 1;
+            s = intersection(q, vertices[k]);
+        }
+        k += 1;
+        vertices[k] = q;
+        boundaries[k] = s;
+        boundaries[k + 1] = f32::INFINITY;
+    }
+
+    k = 0;
+    for (q, value) in output.iter_mut().enumerate() {
+        while boundaries[k + 1] < q as f32 {
+            k += 1;
+        }
+        let offset = q as f32 - vertices[k] as f32;
+        *value = offset * offset + f[vertices[k]];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn image(width: u32, height: u32, format: TextureFormat, data: Vec<u8>) -> Image {
+        Image::new(
+            Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            data,
+            format,
+            RenderAssetUsages::default(),
+        )
+    }
+
+    #[test]
+    fn distances_are_relative_to_the_edge() {
+        // A shape three texels wide in the middle of a row
+        let data = (0..9).map(|x| if (3..6).contains(&x) { 255 } else { 0 });
+        let source = image(9, 1, TextureFormat::R8Unorm, data.collect());
+        let settings = SdfSettings {
+            channel: SdfChannel::Luminance,
+            spread: 4.0,
+            ..Default::default()
+        };
+        let sdf = generate_sdf(&source, &settings).unwrap();
+        assert_eq!(sdf.texture_descriptor.format, TextureFormat::R8Unorm);
+        assert_eq!(sdf.data, [48, 80, 112, 143, 175, 143, 112, 80, 48]);
+    }
+
+    #[test]
+    fn distance_transform_matches_brute_force() {
+        let (width, height) = (7, 5);
+        let mask: Vec<bool> = (0..width * height)
+            .map(|index| (index * 7 + index / 3) % 11 == 0)
+            .collect();
+        let distances = distance_transform(&mask, width, height, true);
+        for y in 0..height {
+            for x in 0..width {
+                let expected = (0..width * height)
+                    .filter(|&index| mask[index])
+                    .map(|index| {
+                        let dx = (index % width) as f32 - x as f32;
+                        let dy = (index / width) as f32 - y as f32;
+                        (dx * dx + dy * dy).sqrt()
+                    })
+                    .fold(f32::INFINITY, f32::min);
+                let distance = distances[y * width + x];
+                assert!(
+                    (distance - expected).abs() < 1e-4,
+                    "{distance} at ({x}, {y})"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn channel_and_threshold_select_the_shape() {
+        // Opaque black, then translucent white
+        let data = vec![0, 0, 0, 255, 255, 255, 255, 64];
+        let source = image(2, 1, TextureFormat::Rgba8Unorm, data);
+        let inside = |channel, threshold| {
+            let settings = SdfSettings {
+                channel,
+                threshold,
+                spread: 1.0,
+            };
+            let sdf = generate_sdf(&source, &settings).unwrap();
+            sdf.data
+                .iter()
+                .map(|&value| value > 127)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(inside(SdfChannel::Alpha, 0.5), [true, false]);
+        assert_eq!(inside(SdfChannel::Alpha, 0.2), [true, true]);
+        assert_eq!(inside(SdfChannel::Luminance, 0.5), [false, true]);
+        // Zero never counts as inside, even with a zero threshold
+        assert_eq!(inside(SdfChannel::Luminance, 0.0), [false, true]);
+    }
+
+    #[test]
+    fn rejects_unsupported_images() {
+        let source = image(1, 1, TextureFormat::R16Float, vec![0; 2]);
+        assert!(matches!(
+            generate_sdf(&source, &SdfSettings::default()),
+            Err(SdfError::UnsupportedFormat(TextureFormat::R16Float))
+        ));
+    }
+}
@@ -1,7 +1,15 @@
-use bevy_color::Color;
+use std::borrow::Cow;
+
+use bevy_asset::Handle;
+use bevy_color::{Color, LinearRgba};
 use bevy_ecs::{component::Component, reflect::ReflectComponent};
-use bevy_math::{Rect, Vec2};
+use bevy_math::{BVec2, Rect, URect, Vec2, Vec3};
 use bevy_reflect::{std_traits::ReflectDefault, Reflect};
+use bevy_render::{
+    extract_component::ExtractComponent,
+    render_resource::{BlendState, Shader},
+    texture::{Image, ImageSamplerDescriptor},
+};
 use bevy_sprite::Anchor;
 
 /// Specifies the rendering properties of a sprite.
@@ -17,6 +25,8 @@ pub struct SpriteEx {
     pub flip_x: bool,
     /// Flip the sprite along the `Y` axis
     pub flip_y: bool,
+    /// The point `flip_x` and `flip_y` mirror the sprite around
+    pub flip_origin: FlipOrigin,
     /// An optional custom size for the sprite that will be used when rendering, instead of the size
     /// of the sprite's image
     pub custom_size: Option<Vec2>,
@@ -26,6 +36,485 @@ pub struct SpriteEx {
     /// When used with a [`TextureAtlas`](crate::TextureAtlas), the rect
     /// is offset by the atlas's minimal (top-left) corner position.
     pub rect: Option<Rect>,
+    /// The coordinate space [`SpriteEx::rect`] is expressed in
+    pub rect_space: RectSpace,
     /// [`Anchor`] point of the sprite in the world
     pub anchor: Anchor,
+    /// Tile the image along each axis instead of stretching it when the sprite's
+    /// [`custom_size`](SpriteEx::custom_size) differs from the size of the image (or of its `rect`)
+    pub repeat: BVec2,
+    /// How the sprite's image is sampled
+    pub sampling: SpriteSampling,
+    /// Treat texels of the sprite's image close to a color as transparent
+    pub color_key: Option<ColorKey>,
+    /// How the sprite's alpha is applied
+    pub alpha_mode: SpriteAlphaMode,
+    /// Brightens the sprite's color by `1.0 + emissive` beyond its tint, so in HDR views the sprite
+    /// can exceed `1.0` and glow with bloom. Views without HDR clamp the result when tonemapping.
+    pub emissive: f32,
+    /// Blends the drawn sprite towards its grayscale luminance, from `0.0` for its normal colors to
+    /// `1.0` for grayscale
+    pub desaturate: f32,
+}
+
+impl SpriteEx {
+    /// Returns [`SpriteEx::rect`] in texels of an image of the given size, resolving
+    /// [`RectSpace::Normalized`] rects against it.
+    pub fn texel_rect(&self, image_size: Vec2) -> Option<Rect> {
+        self.rect.map(|rect| match self.rect_space {
+            RectSpace::Pixels => rect,
+            RectSpace::Normalized => {
+                Rect::from_corners(rect.min * image_size, rect.max * image_size)
+            }
+        })
+    }
+
+    /// Returns the size of the sprite's quad: its custom size, or the size of its rect or `image`.
+    ///
+    /// `None` while it depends on an image that isn't loaded yet.
+    pub fn quad_size(&self, image: Option<&Image>) -> Option<Vec2> {
+        let rect = match self.rect_space {
+            RectSpace::Pixels => self.rect,
+            RectSpace::Normalized => image.and_then(|image| self.texel_rect(image.size_f32())),
+        };
+        self.custom_size
+            .or_else(|| rect.map(|rect| rect.size()))
+            .or_else(|| image.map(Image::size_f32))
+    }
+
+    /// Returns the anchor the sprite's quad is placed with, taking [`FlipOrigin::Anchor`] into
+    /// account.
+    pub fn flipped_anchor(&self) -> Vec2 {
+        let mut anchor = self.anchor.as_vec();
+        if self.flip_origin == FlipOrigin::Anchor {
+            if self.flip_x {
+                anchor.x = -anchor.x;
+            }
+            if self.flip_y {
+                anchor.y = -anchor.y;
+            }
+        }
+        anchor
+    }
+}
+
+/// Makes the texels of a sprite's image that match `color` transparent, for images that use a
+/// background color such as magenta instead of an alpha channel.
+///
+/// Texels match when the distance between their color and `color` in linear RGB is at most
+/// `tolerance`. The test is done on the sampled texture color, before the sprite's tint.
+#[derive(Debug, Clone, Copy, PartialEq, Reflect)]
+#[reflect(Default, PartialEq)]
+pub struct ColorKey {
+    pub color: Color,
+    pub tolerance: f32,
+}
+
+impl Default for ColorKey {
+    fn default() -> Self {
+        Self {
+            color: Color::srgb(1.0, 0.0, 1.0),
+            tolerance: 0.01,
+        }
+    }
+}
+
+/// How a sprite's image is sampled when drawn.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Reflect)]
+#[reflect(Default, PartialEq)]
+pub enum SpriteSampling {
+    /// Sample the image through its sampler.
+    #[default]
+    Texture,
+    /// Upscale the image with a Scale2x (EPX) edge heuristic while sampling, a cheap stand-in for
+    /// RotSprite: each texel is split into quadrants which take the color of a neighbor when the
+    /// neighbors form an edge through it, so diagonal edges of rotated or scaled pixel art stay
+    /// smoother than with nearest-neighbor sampling. It doesn't resample the rotation like
+    /// RotSprite does. Neighbors are only read from within the sprite's rect. This ignores the
+    /// image's sampler and is meant for pixel art.
+    RotSprite,
+}
+
+/// How the alpha of a sprite is applied when drawn.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Reflect)]
+#[reflect(Default, PartialEq)]
+pub enum SpriteAlphaMode {
+    /// Blend the sprite with what is behind it.
+    #[default]
+    Blend,
+    /// Discard fragments with an alpha below the given cutoff, and blend the others.
+    ///
+    /// With a [`depth_format`](crate::SpriteExPlugin::depth_format), these sprites also write
+    /// depth, so sprite art such as foliage occludes correctly regardless of draw order.
+    Mask(f32),
+}
+
+impl SpriteAlphaMode {
+    /// The alpha below which fragments are discarded, `0.0` if none are.
+    pub fn cutoff(&self) -> f32 {
+        match self {
+            SpriteAlphaMode::Blend => 0.0,
+            SpriteAlphaMode::Mask(cutoff) => *cutoff,
+        }
+    }
+}
+
+/// The point a sprite is mirrored around when flipped.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Reflect)]
+#[reflect(Default, PartialEq)]
+pub enum FlipOrigin {
+    /// Mirror the image within the sprite's quad, leaving the quad in place.
+    #[default]
+    Center,
+    /// Mirror the whole quad around the sprite's [`Anchor`] point, so art drawn relative to an
+    /// off-center registration point stays in place when flipped.
+    Anchor,
+}
+
+/// The coordinate space of a sprite's [`rect`](SpriteEx::rect).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Reflect)]
+#[reflect(Default, PartialEq)]
+pub enum RectSpace {
+    /// The rect is given in texels of the sprite's image.
+    #[default]
+    Pixels,
+    /// The rect is given in `0..1` coordinates relative to the image size, and is resolved
+    /// against the actual size of the image at extraction. Rects in this space keep selecting
+    /// the same region when a texture is swapped for one of a different resolution.
+    Normalized,
+}
+
+/// Clips a sprite to a rectangle in the physical pixels of the viewport it is drawn to.
+///
+/// Sprites with different scissor rects are drawn in separate batches.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+#[reflect(Component, PartialEq)]
+pub struct ScissorRect(pub URect);
+
+/// Draws a frame of `color` along the inside of a sprite's edges.
+///
+/// The thickness is given in the sprite's local units, the same as its size, so the frame scales
+/// with the sprite's transform.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Reflect)]
+#[reflect(Component, Default, PartialEq)]
+pub struct Border {
+    pub color: Color,
+    pub thickness: f32,
+}
+
+impl Default for Border {
+    fn default() -> Self {
+        Self {
+            color: Color::WHITE,
+            thickness: 1.0,
+        }
+    }
+}
+
+/// A world-space offset added to a sprite's position when it is extracted for rendering.
+///
+/// The sprite's [`Transform`](bevy_transform::components::Transform) is left untouched, so
+/// effects such as screen shake or parallax jitter can move sprites without conflicting with the
+/// systems that own their transforms. Frustum culling still uses the position without the offset.
+#[derive(Component, Debug, Default, Clone, Copy, PartialEq, Reflect)]
+#[reflect(Component, Default, PartialEq)]
+pub struct ExtractOffset(pub Vec3);
+
+/// An offset added to a sprite's sort key, after the plugin wide
+/// [`SpriteExPlugin::sort_bias`](crate::SpriteExPlugin::sort_bias).
+///
+/// Orders co-planar sprites explicitly, e.g. a weapon with a small positive bias is drawn on top
+/// of the character holding it at the same z. The bias is in the units of the camera's
+/// [`SpriteSortMode`], so it shifts the sprite's z or its negated y.
+#[derive(Component, Debug, Default, Clone, Copy, PartialEq, Reflect)]
+#[reflect(Component, Default, PartialEq)]
+pub struct SortBias(pub f32);
+
+/// Reduces each color channel of a sprite to `levels` values, evenly spaced in linear color space.
+///
+/// Applied in the fragment shader after texture sampling and tinting. Fewer than 2 levels leave the
+/// sprite unchanged.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+#[reflect(Component, Default, PartialEq)]
+pub struct Posterize {
+    pub levels: u32,
+}
+
+impl Default for Posterize {
+    fn default() -> Self {
+        Self { levels: 4 }
+    }
+}
+
+/// Shifts the hue and scales the saturation and value of a sprite's color, e.g. to recolor character
+/// variants from one texture.
+///
+/// Applied in the fragment shader after texture sampling and tinting, before [`Posterize`].
+#[derive(Component, Debug, Clone, Copy, PartialEq, Reflect)]
+#[reflect(Component, Default, PartialEq)]
+pub struct HsvShift {
+    /// Rotation of the hue in degrees
+    pub hue: f32,
+    /// Factor the saturation is multiplied by
+    pub saturation: f32,
+    /// Factor the value is multiplied by
+    pub value: f32,
+}
+
+impl Default for HsvShift {
+    fn default() -> Self {
+        Self {
+            hue: 0.0,
+            saturation: 1.0,
+            value: 1.0,
+        }
+    }
+}
+
+/// Dissolves a sprite through a noise texture, e.g. for death or teleport effects.
+///
+/// The red channel of `noise` is stretched over the sprite's quad. Fragments where it is below
+/// `progress` are discarded, and those less than `edge_width` above it are drawn with
+/// `edge_color`, so animating `progress` from `0.0` to `1.0` burns the sprite away. Sprites are
+/// hidden until their noise texture is loaded, and sprites with different noise textures are never
+/// drawn in the same batch. It has no effect on sprites with a
+/// [`SpriteExMaterial`](crate::SpriteExMaterial).
+#[derive(Component, Debug, Clone, PartialEq, Reflect)]
+#[reflect(Component, Default, PartialEq)]
+pub struct Dissolve {
+    pub noise: Handle<Image>,
+    pub progress: f32,
+    pub edge_width: f32,
+    pub edge_color: Color,
+}
+
+impl Default for Dissolve {
+    fn default() -> Self {
+        Self {
+            noise: Handle::default(),
+            progress: 0.0,
+            edge_width: 0.05,
+            edge_color: Color::srgb(1.0, 0.5, 0.0),
+        }
+    }
+}
+
+/// Marker component starting a new draw batch at this sprite, even if it could be batched with the
+/// previous one.
+///
+/// Useful to get a deterministic batch boundary when custom phase items are drawn between sprites.
+#[derive(Component, Debug, Default, Clone, Copy, PartialEq, Eq, Reflect)]
+#[reflect(Component, Default, PartialEq)]
+pub struct BatchBreak;
+
+/// Overrides how a sprite is blended with what is already drawn, instead of alpha blending.
+///
+/// Each distinct blend state needs its own pipeline, and sprites with different blend states are
+/// never drawn in the same batch. The blend constant used by [`BlendFactor::Constant`] is not set.
+/// Dual-source factors such as [`BlendFactor::Src1`] aren't supported, sprites using them are alpha
+/// blended.
+///
+/// [`BlendFactor::Constant`]: bevy_render::render_resource::BlendFactor::Constant
+/// [`BlendFactor::Src1`]: bevy_render::render_resource::BlendFactor::Src1
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct CustomBlendState(pub BlendState);
+
+/// Samples a sprite's image with this sampler instead of the image's own, e.g. to draw pixel art
+/// with nearest filtering next to smoothly filtered art.
+///
+/// Each distinct image and sampler pair gets its own bind group, and sprites with different
+/// samplers are never drawn in the same batch. It has no effect on the filtering of
+/// [`SpriteSampling::RotSprite`] sprites.
+#[derive(Component, Debug, Clone)]
+pub struct SpriteSampler(pub ImageSamplerDescriptor);
+
+/// Draws a sprite with this fragment shader instead of the one of the sprite pipeline.
+///
+/// The shader gets the same shader defs and bindings as `sprite.wgsl`, and needs a `fragment`
+/// entry point taking a `bevy_sprite_ex::sprite_types::VertexOutput`. Each distinct shader needs
+/// its own pipeline, and sprites with different shaders are never drawn in the same batch. For
+/// extra bindings, use a [`SpriteExMaterial`](crate::SpriteExMaterial) instead.
+#[derive(Component, Debug, Clone, PartialEq)]
+pub struct SpriteFragmentShader(pub Handle<Shader>);
+
+/// Camera component multiplying the color of every sprite drawn by that camera.
+///
+/// Applied in the fragment shader before tonemapping, e.g. for day/night cycles or flashes without
+/// touching the tint of each sprite. Alpha is unchanged. Cameras without it use `1.0`.
+#[derive(Component, ExtractComponent, Debug, Clone, Copy, PartialEq, Reflect)]
+#[reflect(Component, Default, PartialEq)]
+pub struct SpriteExposure(pub f32);
+
+impl Default for SpriteExposure {
+    fn default() -> Self {
+        Self(1.0)
+    }
+}
+
+/// Camera component overriding [`SpriteExPlugin::lod_threshold`] for the sprites drawn by that
+/// camera, e.g. to always draw the full effects in the window of an editor tool.
+///
+/// [`SpriteExPlugin::lod_threshold`]: crate::SpriteExPlugin::lod_threshold
+#[derive(Component, ExtractComponent, Debug, Default, Clone, Copy, PartialEq, Reflect)]
+#[reflect(Component, Default, PartialEq)]
+pub struct SpriteLodThreshold(pub Option<f32>);
+
+/// How sprites are sorted with the other items of the [`Transparent2d`] phase, set for all cameras
+/// with [`SpriteExPlugin::sort_mode`] or for one camera by adding this component to it.
+///
+/// [`Transparent2d`]: bevy_core_pipeline::core_2d::Transparent2d
+/// [`SpriteExPlugin::sort_mode`]: crate::SpriteExPlugin::sort_mode
+#[derive(Component, ExtractComponent, Debug, Default, Clone, Copy, PartialEq, Eq, Reflect)]
+#[reflect(Component, Default, PartialEq)]
+pub enum SpriteSortMode {
+    /// Sort by the z of the sprite's global translation, further back sprites are drawn first.
+    #[default]
+    Z,
+    /// Sort by the y of the sprite's global translation, sprites lower on screen are drawn on top,
+    /// as in top-down games. The z of sprites is ignored, while other items of the phase such as
+    /// `Mesh2d`s keep sorting by their z, which is then compared to the negated y of sprites.
+    Y,
+    /// Sort by layers of z rounded down to whole numbers, then by y within each layer, so e.g. the
+    /// ground at z `0.0` stays below characters at z `1.0` that are Y-sorted between themselves.
+    ///
+    /// Distinguishes y positions about a unit apart up to a few thousand units from the origin.
+    ZLayersThenY,
+}
+
+/// Tints from independent sources added on top of a sprite's color when it is extracted.
+///
+/// Each source, such as a status effect, team color or hit flash, owns one entry keyed by name, so
+/// systems can add and remove their tint without overwriting each other's. Entries are added to the
+/// sprite color in linear space, weighted by their alpha, and don't change its alpha.
+#[derive(Component, Debug, Default, Clone, PartialEq, Reflect)]
+#[reflect(Component, Default, PartialEq)]
+pub struct TintStack {
+    tints: Vec<(Cow<'static, str>, Color)>,
+}
+
+impl TintStack {
+    /// Sets the tint of `source`, replacing its previous one.
+    pub fn set(&mut self, source: impl Into<Cow<'static, str>>, color: impl Into<Color>) {
+        let source = source.into();
+        let color = color.into();
+        match self.tints.iter_mut().find(|(name, _)| *name == source) {
+            Some((_, tint)) => *tint = color,
+            None => self.tints.push((source, color)),
+        }
+    }
+
+    /// Removes the tint of `source`, returning it if there was one.
+    pub fn remove(&mut self, source: &str) -> Option<Color> {
+        let index = self.tints.iter().position(|(name, _)| name == source)?;
+        Some(self.tints.remove(index).1)
+    }
+
+    pub fn get(&self, source: &str) -> Option<Color> {
+        self.tints
+            .iter()
+            .find(|(name, _)| name == source)
+            .map(|(_, color)| *color)
+    }
+
+    pub fn clear(&mut self) {
+        self.tints.clear();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tints.is_empty()
+    }
+
+    /// The sources and their tints, in the order they were first set.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, Color)> {
+        self.tints
+            .iter()
+            .map(|(name, color)| (name.as_ref(), *color))
+    }
+
+    /// Returns `color` with every tint added to it.
+    pub fn apply(&self, color: LinearRgba) -> LinearRgba {
+        self.tints.iter().fold(color, |color, (_, tint)| {
+            let tint = LinearRgba::from(*tint);
+            LinearRgba {
+                red: color.red + tint.red * tint.alpha,
+                green: color.green + tint.green * tint.alpha,
+                blue: color.blue + tint.blue * tint.alpha,
+                alpha: color.alpha,
+            }
+        })
+    }
+}
+
+/// Multiplies the alpha of this entity's sprite and of all descendant sprites when they are
+/// extracted, e.g. to fade out a character together with its equipment and effects.
+///
+/// The opacities of nested groups multiply. Like [`FadeIn`](crate::FadeIn), the colors of the
+/// sprites themselves are left untouched. Each sprite is still blended on its own, so overlapping
+/// sprites of a half transparent group show through each other, see [`SpriteGroup`](crate::SpriteGroup)
+/// to fade them as one layer.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Reflect)]
+#[reflect(Component, Default, PartialEq)]
+pub struct GroupOpacity(pub f32);
+
+impl Default for GroupOpacity {
+    fn default() -> Self {
+        Self(1.0)
+    }
+}
+
+/// Multiplies the color of this entity's sprite and of all descendant sprites when they are
+/// extracted, e.g. to flash a whole prop hierarchy red when it takes damage.
+///
+/// The tint multiplies in linear space, alpha included, and the tints of nested entities multiply.
+/// The colors of the sprites themselves are left untouched.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Reflect)]
+#[reflect(Component, Default, PartialEq)]
+pub struct InheritedTint(pub Color);
+
+impl Default for InheritedTint {
+    fn default() -> Self {
+        Self(Color::WHITE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tint_stack_keeps_one_tint_per_source() {
+        let mut tints = TintStack::default();
+        assert!(tints.is_empty());
+        tints.set("poison", LinearRgba::GREEN);
+        tints.set("team", LinearRgba::BLUE);
+        tints.set("poison", LinearRgba::RED);
+        assert_eq!(
+            tints.iter().collect::<Vec<_>>(),
+            [
+                ("poison", Color::from(LinearRgba::RED)),
+                ("team", Color::from(LinearRgba::BLUE))
+            ]
+        );
+        assert_eq!(tints.get("team"), Some(LinearRgba::BLUE.into()));
+        assert_eq!(tints.remove("poison"), Some(LinearRgba::RED.into()));
+        assert_eq!(tints.remove("poison"), None);
+        assert_eq!(tints.get("poison"), None);
+        tints.clear();
+        assert!(tints.is_empty());
+    }
+
+    #[test]
+    fn tint_stack_adds_tints_weighted_by_alpha() {
+        let mut tints = TintStack::default();
+        let color = LinearRgba::new(0.25, 0.5, 0.0, 0.5);
+        assert_eq!(tints.apply(color), color);
+
+        tints.set("flash", LinearRgba::new(1.0, 0.0, 0.0, 0.5));
+        tints.set("team", LinearRgba::new(0.0, 0.0, 0.5, 1.0));
+        assert_eq!(
+            tints.apply(color),
+            LinearRgba::new(0.75, 0.5, 0.5, 0.5),
+            "the alpha of the sprite is kept"
+        );
+    }
 }
@@ -0,0 +1,192 @@
+use bevy_ecs::{
+    entity::{EntityHashMap, EntityHashSet},
+    prelude::*,
+};
+use bevy_math::{IRect, IVec2, Rect, Vec2, Vec3Swizzles};
+use bevy_render::{
+    camera::{Camera, OrthographicProjection},
+    primitives::Aabb,
+};
+use bevy_transform::components::GlobalTransform;
+use bevy_utils::HashMap;
+
+use crate::{SpriteEx, WithSprite};
+
+/// Sprites covering more cells than this are always extracted instead of being added to the cells.
+const MAX_CELLS_PER_SPRITE: i32 = 64;
+
+/// Spatial grid of sprites, so only sprites near a camera are considered by [`extract_sprites`].
+///
+/// Enabled with [`SpriteExPlugin::spatial_grid_cell_size`]. In worlds with many sprites far away
+/// from every camera, this saves iterating all of them during extraction each frame. Sprites
+/// without an [`Aabb`], very large sprites, and every sprite while a camera isn't orthographic are
+/// still extracted as usual.
+///
+/// [`extract_sprites`]: crate::extract_sprites
+/// [`SpriteExPlugin::spatial_grid_cell_size`]: crate::SpriteExPlugin::spatial_grid_cell_size
+#[derive(Resource, Debug)]
+pub struct SpriteGrid {
+    cell_size: f32,
+    cells: HashMap<IVec2, Vec<Entity>>,
+    /// The cells each sprite in `cells` was added to
+    sprite_cells: EntityHashMap<IRect>,
+    /// Sprites that aren't added to cells and are always candidates
+    unbounded: EntityHashSet,
+    /// Sprites in the cells overlapping a camera, `None` if all sprites are
+    candidates: Option<Vec<Entity>>,
+}
+
+impl SpriteGrid {
+    pub fn new(cell_size: f32) -> Self {
+        Self {
+            cell_size: cell_size.max(f32::EPSILON),
+            cells: HashMap::default(),
+            sprite_cells: EntityHashMap::default(),
+            unbounded: EntityHashSet::default(),
+            candidates: None,
+        }
+    }
+
+    /// The sprites to extract this frame, or `None` if every sprite has to be considered.
+    ///
+    /// Sprites may appear in it without being visible, extraction still checks their visibility.
+    pub fn candidates(&self) -> Option<&[Entity]> {
+        self.candidates.as_deref()
+    }
+
+    fn cell_range(&self, rect: Rect) -> IRect {
+        IRect::from_corners(
+            (rect.min / self.cell_size).floor().as_ivec2(),
+            (rect.max / self.cell_size).floor().as_ivec2(),
+        )
+    }
+
+    fn remove(&mut self, entity: Entity) {
+        self.unbounded.remove(&entity);
+        let Some(range) = self.sprite_cells.remove(&entity) else {
+            return;
+        };
+        for y in range.min.y..=range.max.y {
+            for x in range.min.x..=range.max.x {
+                if let Some(cell) = self.cells.get_mut(&IVec2::new(x, y)) {
+                    cell.retain(|sprite| *sprite != entity);
+                    if cell.is_empty() {
+                        self.cells.remove(&IVec2::new(x, y));
+                    }
+                }
+            }
+        }
+    }
+
+    fn insert(&mut self, entity: Entity, bounds: Option<Rect>) {
+        let Some(range) = bounds.map(|bounds| self.cell_range(bounds)) else {
+            self.unbounded.insert(entity);
+            return;
+        };
+        let size = range.size() + IVec2::ONE;
+        if size.x * size.y > MAX_CELLS_PER_SPRITE {
+            self.unbounded.insert(entity);
+            return;
+        }
+        for y in range.min.y..=range.max.y {
+            for x in range.min.x..=range.max.x {
+                self.cells.entry(IVec2::new(x, y)).or_default().push(entity);
+            }
+        }
+        self.sprite_cells.insert(entity, range);
+    }
+}
+
+/// The world space rectangle covered by the [`Aabb`] of a sprite, in the plane of the camera.
+fn world_bounds(transform: &GlobalTransform, aabb: &Aabb) -> Rect {
+    let affine = transform.affine();
+    let center = affine.transform_point3a(aabb.center).xy();
+    // Extent of the rotated and scaled box along each world axis
+    let half_size = affine.matrix3.x_axis.xy().abs() * aabb.half_extents.x
+        + affine.matrix3.y_axis.xy().abs() * aabb.half_extents.y
+        + affine.matrix3.z_axis.xy().abs() * aabb.half_extents.z;
+    Rect::from_center_half_size(center, half_size)
+}
+
+/// System moving sprites whose transform or bounds changed to their new cells of the [`SpriteGrid`].
+pub fn update_sprite_grid(
+    mut grid: ResMut<SpriteGrid>,
+    changed_sprites: Query<
+        (Entity, &GlobalTransform, Option<&Aabb>),
+        (WithSprite, Or<(Changed<GlobalTransform>, Changed<Aabb>)>),
+    >,
+    mut removed_sprites: RemovedComponents<SpriteEx>,
+    mut removed_aabbs: RemovedComponents<Aabb>,
+    sprites: Query<(&GlobalTransform, Option<&Aabb>), WithSprite>,
+) {
+    for entity in removed_sprites.read() {
+        grid.remove(entity);
+    }
+    for entity in removed_aabbs.read() {
+        if let Ok((transform, aabb)) = sprites.get(entity) {
+            grid.remove(entity);
+            let bounds = aabb.map(|aabb| world_bounds(transform, aabb));
+            grid.insert(entity, bounds);
+        }
+    }
+    for (entity, transform, aabb) in &changed_sprites {
+        grid.remove(entity);
+        let bounds = aabb.map(|aabb| world_bounds(transform, aabb));
+        grid.insert(entity, bounds);
+    }
+}
+
+/// System collecting the [`SpriteGrid::candidates`] in the cells overlapping an active camera.
+pub fn collect_sprite_grid_candidates(
+    mut grid: ResMut<SpriteGrid>,
+    cameras: Query<(&Camera, &GlobalTransform, Option<&OrthographicProjection>)>,
+) {
+    let mut views = Vec::new();
+    for (camera, transform, projection) in &cameras {
+        if !camera.is_active {
+            continue;
+        }
+        // The area seen by other projections isn't bounded in the sprite plane
+        let Some(projection) = projection else {
+            grid.candidates = None;
+            return;
+        };
+        let affine = transform.affine();
+        let corners = [
+            projection.area.min,
+            Vec2::new(projection.area.max.x, projection.area.min.y),
+            projection.area.max,
+            Vec2::new(projection.area.min.x, projection.area.max.y),
+        ]
+        .map(|corner| affine.transform_point3(corner.extend(0.0)).xy());
+        let area = corners.iter().fold(
+            Rect::from_corners(corners[0], corners[0]),
+            |area, corner| area.union_point(*corner),
+        );
+        views.push(grid.cell_range(area));
+    }
+
+    let grid = &mut *grid;
+    let mut seen = EntityHashSet::default();
+    let mut candidates: Vec<Entity> = grid.unbounded.iter().copied().collect();
+    for range in views {
+        let range_size = range.size() + IVec2::ONE;
+        // Walk the occupied cells instead when the view covers more cells than there are
+        if (range_size.x as i64) * (range_size.y as i64) > grid.cells.len() as i64 {
+            for (cell, sprites) in &grid.cells {
+                if range.contains(*cell) {
+                    candidates.extend(sprites.iter().filter(|sprite| seen.insert(**sprite)));
+                }
+            }
+            continue;
+        }
+        for y in range.min.y..=range.max.y {
+            for x in range.min.x..=range.max.x {
+                if let Some(sprites) = grid.cells.get(&IVec2::new(x, y)) {
+                    candidates.extend(sprites.iter().filter(|sprite| seen.insert(**sprite)));
+                }
+            }
+        }
+    }
+    grid.candidates = Some(candidates);
+}
@@ -0,0 +1,210 @@
+use bevy_asset::{Assets, Handle};
+use bevy_color::Color;
+use bevy_core_pipeline::core_2d::Camera2dBundle;
+use bevy_ecs::prelude::*;
+use bevy_hierarchy::{BuildChildren, Children, DespawnRecursiveExt};
+use bevy_math::{UVec2, Vec2};
+use bevy_reflect::{std_traits::ReflectDefault, Reflect};
+use bevy_render::{
+    camera::{Camera, ClearColorConfig, RenderTarget},
+    render_asset::RenderAssetUsages,
+    render_resource::{BlendState, Extent3d, TextureDimension, TextureFormat, TextureUsages},
+    texture::Image,
+    view::RenderLayers,
+};
+
+use crate::{CustomBlendState, SpriteEx};
+
+/// Renders the descendants of a [`SpriteEx`] into an offscreen image, which the sprite then draws
+/// as a whole, so the group is faded and blended as one layer instead of sprite by sprite.
+///
+/// The sprite's image, custom size and color are managed by the group, and restored when the group
+/// is removed. The descendants are moved to `render_layer`, which only the camera rendering the
+/// image should see, a [`SpriteGroupCamera`] spawned as a child of the group on that layer. The
+/// image is centered on the group and covers `size` units of the group's local space, descendants
+/// outside of it are cut off.
+///
+/// The image holds premultiplied colors, so the group is drawn with premultiplied alpha blending
+/// unless it has its own [`CustomBlendState`]. Groups can't be nested, the descendants of a nested
+/// group keep its render layer and are drawn in whatever order the two cameras run in.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Reflect)]
+#[reflect(Component, Default, PartialEq)]
+pub struct SpriteGroup {
+    /// The size of the offscreen image in pixels
+    pub size: UVec2,
+    /// The render layer of the descendants, shouldn't be seen by any other camera
+    pub render_layer: usize,
+    /// Multiplies the color of the whole group
+    pub opacity: f32,
+}
+
+impl Default for SpriteGroup {
+    fn default() -> Self {
+        Self {
+            size: UVec2::splat(256),
+            render_layer: 1,
+            opacity: 1.0,
+        }
+    }
+}
+
+/// Marker component of the cameras rendering the image of a [`SpriteGroup`].
+#[derive(Component, Debug, Default, Clone, Copy, PartialEq, Eq, Reflect)]
+#[reflect(Component, Default, PartialEq)]
+pub struct SpriteGroupCamera;
+
+/// The camera of a [`SpriteGroup`] and the state of its sprite from before the group, restored
+/// when the group is removed.
+#[derive(Component, Debug, Clone)]
+pub struct SpriteGroupState {
+    camera: Entity,
+    image: Handle<Image>,
+    custom_size: Option<Vec2>,
+    color: Color,
+    inserted_blend_state: bool,
+}
+
+/// System creating the image and camera of new [`SpriteGroup`]s, applying changes to them and
+/// moving their descendants to the group's render layer.
+pub fn update_sprite_groups(
+    mut commands: Commands,
+    mut images: ResMut<Assets<Image>>,
+    mut groups: Query<(
+        Entity,
+        Ref<SpriteGroup>,
+        &mut SpriteEx,
+        &mut Handle<Image>,
+        Has<CustomBlendState>,
+        Option<&SpriteGroupState>,
+    )>,
+    children: Query<&Children>,
+    nested_groups: Query<(), With<SpriteGroup>>,
+    group_cameras: Query<(), With<SpriteGroupCamera>>,
+    render_layers: Query<&RenderLayers>,
+) {
+    for (entity, group, mut sprite, mut image, custom_blend_state, state) in &mut groups {
+        let layers = RenderLayers::layer(group.render_layer);
+        if group.is_changed() {
+            let size = group.size.max(UVec2::ONE);
+            let extent = Extent3d {
+                width: size.x,
+                height: size.y,
+                depth_or_array_layers: 1,
+            };
+            match state.filter(|_| !group.is_added()) {
+                Some(state) => {
+                    if let Some(target) = images.get_mut(&*image) {
+                        target.resize(extent);
+                    }
+                    commands.entity(state.camera).insert(layers.clone());
+                }
+                None => {
+                    let mut target = Image::new_fill(
+                        extent,
+                        TextureDimension::D2,
+                        &[0; 4],
+                        TextureFormat::Bgra8UnormSrgb,
+                        RenderAssetUsages::default(),
+                    );
+                    target.texture_descriptor.usage = TextureUsages::TEXTURE_BINDING
+                        | TextureUsages::COPY_DST
+                        | TextureUsages::RENDER_ATTACHMENT;
+                    let previous_image = std::mem::replace(&mut *image, images.add(target));
+                    let camera = commands
+                        .spawn((
+                            Camera2dBundle {
+                                camera: Camera {
+                                    // Before the cameras drawing the group
+                                    order: -1,
+                                    target: RenderTarget::Image(image.clone()),
+                                    clear_color: ClearColorConfig::Custom(Color::NONE),
+                                    ..Default::default()
+                                },
+                                ..Default::default()
+                            },
+                            layers.clone(),
+                            SpriteGroupCamera,
+                        ))
+                        .id();
+                    commands
+                        .entity(entity)
+                        .add_child(camera)
+                        .insert(SpriteGroupState {
+                            camera,
+                            image: previous_image,
+                            custom_size: sprite.custom_size,
+                            color: sprite.color,
+                            inserted_blend_state: !custom_blend_state,
+                        });
+                    if !custom_blend_state {
+                        commands
+                            .entity(entity)
+                            .insert(CustomBlendState(BlendState::PREMULTIPLIED_ALPHA_BLENDING));
+                    }
+                }
+            }
+            let opacity = group.opacity.clamp(0.0, 1.0);
+            sprite.custom_size = Some(size.as_vec2());
+            sprite.color = Color::linear_rgba(opacity, opacity, opacity, opacity);
+        }
+
+        let mut stack: Vec<Entity> = children
+            .get(entity)
+            .into_iter()
+            .flatten()
+            .copied()
+            .collect();
+        while let Some(descendant) = stack.pop() {
+            if group_cameras.contains(descendant) {
+                continue;
+            }
+            if render_layers.get(descendant) != Ok(&layers) {
+                commands.entity(descendant).insert(layers.clone());
+            }
+            if !nested_groups.contains(descendant) {
+                stack.extend(children.get(descendant).into_iter().flatten());
+            }
+        }
+    }
+}
+
+/// System despawning the camera of removed [`SpriteGroup`]s, restoring the image, custom size,
+/// color and blend state of their sprite and moving their descendants back to the default render
+/// layer.
+pub fn remove_sprite_groups(
+    mut commands: Commands,
+    mut removed: RemovedComponents<SpriteGroup>,
+    mut sprites: Query<(&SpriteGroupState, &mut SpriteEx, &mut Handle<Image>)>,
+    children: Query<&Children>,
+    group_cameras: Query<(), With<SpriteGroupCamera>>,
+    nested_groups: Query<(), With<SpriteGroup>>,
+) {
+    for entity in removed.read() {
+        if let Ok((state, mut sprite, mut image)) = sprites.get_mut(entity) {
+            *image = state.image.clone();
+            sprite.custom_size = state.custom_size;
+            sprite.color = state.color;
+            let mut entity_commands = commands.entity(entity);
+            entity_commands.remove::<SpriteGroupState>();
+            if state.inserted_blend_state {
+                entity_commands.remove::<CustomBlendState>();
+            }
+        }
+        let mut stack: Vec<Entity> = children
+            .get(entity)
+            .into_iter()
+            .flatten()
+            .copied()
+            .collect();
+        while let Some(descendant) = stack.pop() {
+            if group_cameras.contains(descendant) {
+                commands.entity(descendant).despawn_recursive();
+                continue;
+            }
+            commands.entity(descendant).remove::<RenderLayers>();
+            if !nested_groups.contains(descendant) {
+                stack.extend(children.get(descendant).into_iter().flatten());
+            }
+        }
+    }
+}
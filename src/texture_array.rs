@@ -0,0 +1,368 @@
+use bevy_asset::{AssetEvent, AssetId, Assets};
+use bevy_ecs::prelude::*;
+use bevy_math::{URect, UVec2, Vec2Swizzles, Vec4};
+use bevy_render::{
+    render_resource::{
+        BindGroup, BindGroupEntries, Extent3d, ImageCopyTexture, ImageDataLayout, Origin3d,
+        Texture, TextureAspect, TextureDescriptor, TextureDimension, TextureFormat, TextureUsages,
+        TextureView, TextureViewDescriptor, TextureViewDimension,
+    },
+    renderer::{RenderDevice, RenderQueue},
+    texture::{DefaultImageSampler, Image, ImageSampler},
+    Extract,
+};
+use bevy_utils::{HashMap, HashSet};
+
+use crate::{
+    ExtractedSprite, ExtractedSprites, SpriteAssetEvents, SpriteExPipeline,
+    SpriteExPipelineSettings, SpriteSampling,
+};
+
+/// Settings of the [`SpriteTextureArray`], see
+/// [`SpriteExPlugin::texture_array`](crate::SpriteExPlugin::texture_array).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpriteTextureArraySettings {
+    /// The size of each layer, images larger than it along either axis aren't added
+    pub layer_size: UVec2,
+    /// The number of layers, limited by the device's `max_texture_array_layers`
+    pub layers: u32,
+}
+
+impl Default for SpriteTextureArraySettings {
+    fn default() -> Self {
+        Self {
+            layer_size: UVec2::splat(64),
+            layers: 256,
+        }
+    }
+}
+
+/// A texture array small sprite images are packed into at runtime, so sprites with different
+/// images can be drawn in the same batch.
+///
+/// Images are added once a sprite using them is extracted, if they are non-empty `Rgba8UnormSrgb`
+/// 2D images without mipmaps, use the default sampler, fit in a layer and still have their data in
+/// the main world. They are packed into rows of each layer, with a transparent gutter of one texel
+/// between them, and sampled with the default image sampler. With a linear sampler, the edges of
+/// images blend with the gutter. A layer is reused once all its images are modified or removed.
+///
+/// Sprites with a material, a [`SpriteFragmentShader`], a [`SpriteSampler`] or
+/// [`SpriteSampling::RotSprite`] are drawn with their own image as usual.
+///
+/// [`SpriteFragmentShader`]: crate::SpriteFragmentShader
+/// [`SpriteSampler`]: crate::SpriteSampler
+#[derive(Resource)]
+pub struct SpriteTextureArray {
+    settings: SpriteTextureArraySettings,
+    texture: Texture,
+    bind_group: BindGroup,
+    /// The layer and the rect in it of each image in the array
+    regions: HashMap<AssetId<Image>, (u32, URect)>,
+    layers: Vec<LayerPacker>,
+    /// The data of images packed this frame, written to their regions in
+    /// [`prepare_sprite_texture_array`]
+    pending: Vec<(AssetId<Image>, Vec<u8>)>,
+    /// Images that can't be added to the array
+    rejected: HashSet<AssetId<Image>>,
+}
+
+impl FromWorld for SpriteTextureArray {
+    fn from_world(world: &mut World) -> Self {
+        let render_device = world.resource::<RenderDevice>();
+        let mut settings = world
+            .resource::<SpriteExPipelineSettings>()
+            .texture_array
+            .unwrap_or_default();
+        settings.layer_size = settings.layer_size.max(UVec2::ONE);
+        settings.layers = settings
+            .layers
+            .clamp(1, render_device.limits().max_texture_array_layers);
+        let texture = render_device.create_texture(&TextureDescriptor {
+            label: Some("sprite_texture_array"),
+            size: Extent3d {
+                width: settings.layer_size.x,
+                height: settings.layer_size.y,
+                depth_or_array_layers: settings.layers,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: TextureFormat::Rgba8UnormSrgb,
+            usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        let view: TextureView = texture.create_view(&TextureViewDescriptor {
+            dimension: Some(TextureViewDimension::D2Array),
+            ..Default::default()
+        });
+        let bind_group = render_device.create_bind_group(
+            "sprite_texture_array_bind_group",
+            &world.resource::<SpriteExPipeline>().texture_array_layout,
+            &BindGroupEntries::sequential((&view, &**world.resource::<DefaultImageSampler>())),
+        );
+        Self {
+            settings,
+            texture,
+            bind_group,
+            regions: HashMap::default(),
+            layers: (0..settings.layers)
+                .map(|_| LayerPacker::default())
+                .collect(),
+            pending: Vec::new(),
+            rejected: HashSet::default(),
+        }
+    }
+}
+
+impl SpriteTextureArray {
+    /// The layer and the rect in it of the image of `sprite`, if it is drawn from the array.
+    pub fn region(&self, sprite: &ExtractedSprite) -> Option<(u32, URect)> {
+        if !Self::accepts(sprite) {
+            return None;
+        }
+        self.regions.get(&sprite.image_handle_id).copied()
+    }
+
+    /// The bind group of the array, bound instead of the image bind group.
+    pub fn bind_group(&self) -> &BindGroup {
+        &self.bind_group
+    }
+
+    /// Remaps `uv_offset_scale` (offset in `xy`, scale in `zw`) from UVs of an image to UVs of its
+    /// `rect` in the array.
+    pub fn remap_uv_offset_scale(&self, rect: URect, uv_offset_scale: Vec4) -> Vec4 {
+        let layer_size = self.settings.layer_size.as_vec2();
+        let scale = rect.size().as_vec2() / layer_size;
+        let offset = rect.min.as_vec2() / layer_size;
+        uv_offset_scale * scale.xyxy() + offset.extend(0.0).extend(0.0)
+    }
+
+    fn accepts(sprite: &ExtractedSprite) -> bool {
+        sprite.material.is_none()
+            && sprite.fragment_shader.is_none()
+            && sprite.sampler.is_none()
+            && sprite.sampling == SpriteSampling::Texture
+    }
+
+    fn can_hold(&self, image: &Image) -> bool {
+        let descriptor = &image.texture_descriptor;
+        let size = image.size();
+        descriptor.format == TextureFormat::Rgba8UnormSrgb
+            && descriptor.dimension == TextureDimension::D2
+            && descriptor.size.depth_or_array_layers == 1
+            && descriptor.mip_level_count == 1
+            && matches!(image.sampler, ImageSampler::Default)
+            && size.cmpgt(UVec2::ZERO).all()
+            && size.cmple(self.settings.layer_size).all()
+            && image.data.len() == (size.x * size.y * 4) as usize
+    }
+
+    /// Packs an image of `size` into the first layer with room for it.
+    fn allocate(&mut self, size: UVec2) -> Option<(u32, URect)> {
+        let layer_size = self.settings.layer_size;
+        self.layers
+            .iter_mut()
+            .enumerate()
+            .find_map(|(layer, packer)| Some((layer as u32, packer.allocate(size, layer_size)?)))
+    }
+
+    fn release(&mut self, id: AssetId<Image>) {
+        self.rejected.remove(&id);
+        if let Some((layer, _)) = self.regions.remove(&id) {
+            self.layers[layer as usize].release();
+        }
+    }
+}
+
+/// Packs images into rows of a layer, from top to bottom.
+///
+/// Rects aren't reused one by one, the whole layer is cleared once all its images are released.
+#[derive(Default)]
+struct LayerPacker {
+    /// The top, the height and the x of the next image of each row
+    rows: Vec<(u32, u32, u32)>,
+    images: u32,
+}
+
+impl LayerPacker {
+    /// Transparent texels kept to the right of and below each image
+    const GUTTER: u32 = 1;
+
+    fn allocate(&mut self, size: UVec2, layer_size: UVec2) -> Option<URect> {
+        let fits_row =
+            |(_, height, x): &(u32, u32, u32)| size.y <= *height && x + size.x <= layer_size.x;
+        // The first row tall enough, or a new row below the others
+        let row = match self.rows.iter().position(fits_row) {
+            Some(row) => row,
+            None => {
+                let top = self
+                    .rows
+                    .last()
+                    .map_or(0, |(top, height, _)| top + height + Self::GUTTER);
+                if top + size.y > layer_size.y {
+                    return None;
+                }
+                self.rows.push((top, size.y, 0));
+                self.rows.len() - 1
+            }
+        };
+        let (top, _, x) = &mut self.rows[row];
+        let min = UVec2::new(*x, *top);
+        *x += size.x + Self::GUTTER;
+        self.images += 1;
+        Some(URect::from_corners(min, min + size))
+    }
+
+    fn release(&mut self) {
+        self.images = self.images.saturating_sub(1);
+        if self.images == 0 {
+            self.rows.clear();
+        }
+    }
+}
+
+/// Packs the images of extracted sprites that can be added to the [`SpriteTextureArray`], and
+/// extracts their data.
+pub fn extract_sprite_texture_array_images(
+    mut texture_array: ResMut<SpriteTextureArray>,
+    extracted_sprites: Res<ExtractedSprites>,
+    images: Extract<Res<Assets<Image>>>,
+) {
+    let texture_array = &mut *texture_array;
+    for sprite in extracted_sprites.sprites.values() {
+        let id = sprite.image_handle_id;
+        if !SpriteTextureArray::accepts(sprite)
+            || texture_array.regions.contains_key(&id)
+            || texture_array.rejected.contains(&id)
+        {
+            continue;
+        }
+        let Some(image) = images.get(id) else {
+            continue;
+        };
+        if !texture_array.can_hold(image) {
+            texture_array.rejected.insert(id);
+            continue;
+        }
+        // Images that don't fit anymore are tried again once a layer is cleared
+        let Some(region) = texture_array.allocate(image.size()) else {
+            continue;
+        };
+        texture_array.regions.insert(id, region);
+        texture_array.pending.push((id, image.data.clone()));
+    }
+}
+
+/// Releases the regions of changed and removed images, and writes the images packed this frame
+/// to their regions of the [`SpriteTextureArray`].
+pub fn prepare_sprite_texture_array(
+    mut texture_array: ResMut<SpriteTextureArray>,
+    events: Res<SpriteAssetEvents>,
+    render_queue: Res<RenderQueue>,
+) {
+    for event in &events.images {
+        match event {
+            AssetEvent::Modified { id }
+            | AssetEvent::Unused { id }
+            | AssetEvent::Removed { id } => {
+                texture_array.release(*id);
+            }
+            _ => {}
+        }
+    }
+
+    let texture_array = &mut *texture_array;
+    let layer_size = texture_array.settings.layer_size;
+    for (id, data) in texture_array.pending.drain(..) {
+        // Released in the frame it was packed
+        let Some(&(layer, rect)) = texture_array.regions.get(&id) else {
+            continue;
+        };
+        // Also clear the gutter, which may hold texels of images of a cleared layer
+        let size = rect.size();
+        let padded_size = (rect.max + LayerPacker::GUTTER).min(layer_size) - rect.min;
+        let mut padded = vec![0; (padded_size.x * padded_size.y * 4) as usize];
+        for (y, row) in data.chunks_exact((size.x * 4) as usize).enumerate() {
+            let start = y * (padded_size.x * 4) as usize;
+            padded[start..start + row.len()].copy_from_slice(row);
+        }
+        render_queue.write_texture(
+            ImageCopyTexture {
+                texture: &texture_array.texture,
+                mip_level: 0,
+                origin: Origin3d {
+                    x: rect.min.x,
+                    y: rect.min.y,
+                    z: layer,
+                },
+                aspect: TextureAspect::All,
+            },
+            &padded,
+            ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(padded_size.x * 4),
+                rows_per_image: None,
+            },
+            Extent3d {
+                width: padded_size.x,
+                height: padded_size.y,
+                depth_or_array_layers: 1,
+            },
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LAYER_SIZE: UVec2 = UVec2::splat(8);
+
+    fn rect(min: (u32, u32), max: (u32, u32)) -> Option<URect> {
+        Some(URect::new(min.0, min.1, max.0, max.1))
+    }
+
+    #[test]
+    fn packs_rows_with_gutters() {
+        let mut packer = LayerPacker::default();
+        let mut allocate = |x, y| packer.allocate(UVec2::new(x, y), LAYER_SIZE);
+        assert_eq!(allocate(3, 2), rect((0, 0), (3, 2)));
+        assert_eq!(allocate(3, 2), rect((4, 0), (7, 2)));
+        // The first row has no room left, so a new row starts below its gutter
+        assert_eq!(allocate(3, 2), rect((0, 3), (3, 5)));
+        // Shorter images go into the first row tall enough with room for them
+        assert_eq!(allocate(2, 1), rect((4, 3), (6, 4)));
+        // Too tall for a new row
+        assert_eq!(allocate(1, 3), None);
+        assert_eq!(allocate(1, 2), rect((7, 3), (8, 5)));
+        assert_eq!(allocate(2, 2), rect((0, 6), (2, 8)));
+    }
+
+    #[test]
+    fn fills_the_whole_layer() {
+        let mut packer = LayerPacker::default();
+        assert_eq!(
+            packer.allocate(LAYER_SIZE, LAYER_SIZE),
+            rect((0, 0), (8, 8))
+        );
+        assert_eq!(packer.allocate(UVec2::ONE, LAYER_SIZE), None);
+        assert_eq!(packer.allocate(UVec2::new(9, 1), LAYER_SIZE), None);
+    }
+
+    #[test]
+    fn clears_once_every_image_is_released() {
+        let mut packer = LayerPacker::default();
+        packer.allocate(UVec2::new(8, 4), LAYER_SIZE);
+        packer.allocate(UVec2::new(8, 3), LAYER_SIZE);
+        assert_eq!(packer.allocate(UVec2::new(8, 4), LAYER_SIZE), None);
+
+        // Released rects aren't reused while the layer still holds images
+        packer.release();
+        assert_eq!(packer.allocate(UVec2::new(8, 4), LAYER_SIZE), None);
+        packer.release();
+        assert_eq!(
+            packer.allocate(UVec2::new(8, 4), LAYER_SIZE),
+            rect((0, 0), (8, 4))
+        );
+    }
+}
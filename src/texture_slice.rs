@@ -0,0 +1,126 @@
+use bevy_asset::{AssetEvent, Assets, Handle};
+use bevy_ecs::prelude::*;
+use bevy_math::{Rect, Vec2};
+use bevy_render::texture::Image;
+use bevy_sprite::{ImageScaleMode, TextureSlice};
+use bevy_utils::HashSet;
+
+use crate::SpriteEx;
+
+/// Component storing the texture slices of [`SpriteEx`] entities with an [`ImageScaleMode`].
+///
+/// This component is automatically inserted and updated. Each slice is drawn as its own quad, so
+/// per quad effects such as a [`Border`](crate::Border) apply to every slice.
+#[derive(Debug, Clone, Component)]
+pub struct ComputedSlices {
+    slices: Vec<TextureSlice>,
+    /// The size of the whole sprite the slices are laid out in
+    size: Vec2,
+}
+
+impl ComputedSlices {
+    /// The slices, with offsets relative to the center of the sprite.
+    pub fn slices(&self) -> &[TextureSlice] {
+        &self.slices
+    }
+
+    /// Returns the anchor of `slice` placing it at its position in a sprite with the given anchor.
+    pub(crate) fn slice_anchor(&self, slice: &TextureSlice, sprite_anchor: Vec2) -> Vec2 {
+        if slice.draw_size.cmpeq(Vec2::ZERO).any() {
+            sprite_anchor
+        } else {
+            sprite_anchor * self.size / slice.draw_size
+        }
+    }
+}
+
+/// Generates the slices of `sprite` for `scale_mode`, from the image size or the sprite rect.
+///
+/// Returns `None` if the image asset is not loaded.
+fn compute_sprite_slices(
+    sprite: &SpriteEx,
+    scale_mode: &ImageScaleMode,
+    image_handle: &Handle<Image>,
+    images: &Assets<Image>,
+) -> Option<ComputedSlices> {
+    let image_size = images.get(image_handle)?.size_f32();
+    let texture_rect = sprite.texel_rect(image_size).unwrap_or(Rect {
+        min: Vec2::ZERO,
+        max: image_size,
+    });
+    let slices = match scale_mode {
+        ImageScaleMode::Sliced(slicer) => slicer.compute_slices(texture_rect, sprite.custom_size),
+        ImageScaleMode::Tiled {
+            tile_x,
+            tile_y,
+            stretch_value,
+        } => {
+            let slice = TextureSlice {
+                texture_rect,
+                draw_size: sprite.custom_size.unwrap_or(texture_rect.size()),
+                offset: Vec2::ZERO,
+            };
+            slice.tiled(*stretch_value, (*tile_x, *tile_y))
+        }
+    };
+    Some(ComputedSlices {
+        slices,
+        size: sprite.custom_size.unwrap_or(texture_rect.size()),
+    })
+}
+
+/// System recomputing the slices of [`SpriteEx`] entities with an [`ImageScaleMode`] whose image was
+/// added or modified.
+pub fn compute_slices_on_asset_event(
+    mut commands: Commands,
+    mut events: EventReader<AssetEvent<Image>>,
+    images: Res<Assets<Image>>,
+    sprites: Query<(Entity, &ImageScaleMode, &SpriteEx, &Handle<Image>)>,
+) {
+    let changed_images: HashSet<_> = events
+        .read()
+        .filter_map(|event| match event {
+            AssetEvent::Added { id } | AssetEvent::Modified { id } => Some(*id),
+            _ => None,
+        })
+        .collect();
+    if changed_images.is_empty() {
+        return;
+    }
+    for (entity, scale_mode, sprite, image_handle) in &sprites {
+        if !changed_images.contains(&image_handle.id()) {
+            continue;
+        }
+        if let Some(slices) = compute_sprite_slices(sprite, scale_mode, image_handle, &images) {
+            commands.entity(entity).insert(slices);
+        }
+    }
+}
+
+/// System computing the slices of [`SpriteEx`] entities whose [`ImageScaleMode`], sprite or image
+/// handle changed, and removing them from entities which lost their [`ImageScaleMode`].
+pub fn compute_slices_on_sprite_change(
+    mut commands: Commands,
+    images: Res<Assets<Image>>,
+    changed_sprites: Query<
+        (Entity, &ImageScaleMode, &SpriteEx, &Handle<Image>),
+        Or<(
+            Changed<ImageScaleMode>,
+            Changed<SpriteEx>,
+            Changed<Handle<Image>>,
+        )>,
+    >,
+    mut removed_scale_modes: RemovedComponents<ImageScaleMode>,
+    sliced_sprites: Query<(), (With<ComputedSlices>, Without<ImageScaleMode>)>,
+) {
+    for entity in removed_scale_modes.read() {
+        if sliced_sprites.contains(entity) {
+            commands.entity(entity).remove::<ComputedSlices>();
+        }
+    }
+    for (entity, scale_mode, sprite, image_handle) in &changed_sprites {
+        if let Some(slices) = compute_sprite_slices(sprite, scale_mode, image_handle, &images) {
+            commands.entity(entity).insert(slices);
+        }
+    }
+}